@@ -0,0 +1,28 @@
+PRAGMA defer_foreign_keys = ON;
+
+create table accounts_tmp (
+    id integer not null primary key,
+    name text not null collate nocase,
+    login_code_prefix text not null,
+    login_code_hash text not null,
+    updated_at int not null,
+    created_at int not null
+);
+
+-- existing codes are marked as plain and get hashed by Database::migrate
+-- right after this migration runs, the server needs the key to hash them
+insert into accounts_tmp (
+    id, name, login_code_prefix, login_code_hash, updated_at, created_at
+) select id, name, substr(login_code, 1, 6), 'plain:' || login_code, updated_at, created_at
+from accounts;
+
+drop index accounts_name;
+drop index accounts_login_code;
+drop table accounts;
+
+alter table accounts_tmp rename to accounts;
+create unique index accounts_name on accounts(name);
+create index accounts_login_code_prefix on accounts(login_code_prefix);
+create unique index accounts_login_code_hash on accounts(login_code_hash);
+
+PRAGMA defer_foreign_keys = OFF;
@@ -0,0 +1,4 @@
+drop index moderation_actions_created_at;
+drop table moderation_actions;
+alter table accounts drop column suspended_at;
+alter table accounts drop column is_admin;
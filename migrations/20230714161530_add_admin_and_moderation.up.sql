@@ -0,0 +1,16 @@
+alter table accounts add column is_admin int not null default 0;
+alter table accounts add column suspended_at int;
+
+-- no foreign keys here, the audit trail outlives what it points at
+create table moderation_actions (
+    id integer primary key,
+    moderator_id integer not null,
+    action text not null,
+    target_kind text not null,
+    target_id integer not null,
+    summary text not null,
+    updated_at int not null,
+    created_at int not null
+);
+
+create index moderation_actions_created_at on moderation_actions(created_at);
@@ -0,0 +1,3 @@
+drop index reports_target_status;
+drop index reports_reporter_target;
+drop table reports;
@@ -0,0 +1,14 @@
+create table reports (
+    id integer primary key,
+    reporter_id integer not null references accounts(id),
+    target_kind text not null,
+    target_id integer not null,
+    reason text not null,
+    status text not null default 'open',
+    resolved_by integer,
+    updated_at int not null,
+    created_at int not null
+);
+
+create unique index reports_reporter_target on reports(reporter_id, target_kind, target_id);
+create index reports_target_status on reports(target_kind, target_id, status);
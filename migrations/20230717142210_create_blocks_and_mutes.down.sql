@@ -0,0 +1,4 @@
+drop index mutes_muter_muted;
+drop table mutes;
+drop index blocks_blocker_blocked;
+drop table blocks;
@@ -0,0 +1,19 @@
+create table blocks (
+    id integer primary key,
+    blocker_id integer not null references accounts(id),
+    blocked_id integer not null references accounts(id),
+    updated_at int not null,
+    created_at int not null
+);
+
+create unique index blocks_blocker_blocked on blocks(blocker_id, blocked_id);
+
+create table mutes (
+    id integer primary key,
+    muter_id integer not null references accounts(id),
+    muted_id integer not null references accounts(id),
+    updated_at int not null,
+    created_at int not null
+);
+
+create unique index mutes_muter_muted on mutes(muter_id, muted_id);
@@ -0,0 +1,2 @@
+alter table accounts drop column deletion_mode;
+alter table accounts drop column deletion_requested_at;
@@ -0,0 +1,2 @@
+alter table accounts add column deletion_requested_at int;
+alter table accounts add column deletion_mode text;
@@ -0,0 +1,2 @@
+drop index drafts_account_id;
+drop table drafts;
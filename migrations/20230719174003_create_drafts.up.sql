@@ -0,0 +1,9 @@
+create table drafts (
+    id integer primary key,
+    account_id integer not null references accounts(id),
+    body text not null,
+    updated_at int not null,
+    created_at int not null
+);
+
+create index drafts_account_id on drafts(account_id);
@@ -0,0 +1 @@
+alter table posts add column publish_at int;
@@ -0,0 +1,3 @@
+drop index attachments_account_id;
+drop index attachments_post_id;
+drop table attachments;
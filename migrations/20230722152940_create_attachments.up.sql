@@ -0,0 +1,13 @@
+create table attachments (
+    id integer primary key,
+    account_id integer not null references accounts(id),
+    post_id integer references posts(id),
+    filename text not null,
+    width int not null,
+    height int not null,
+    updated_at int not null,
+    created_at int not null
+);
+
+create index attachments_post_id on attachments(post_id);
+create index attachments_account_id on attachments(account_id);
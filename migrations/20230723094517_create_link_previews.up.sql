@@ -0,0 +1,7 @@
+create table link_previews (
+    url text primary key not null,
+    title text,
+    description text,
+    image_url text,
+    fetched_at int not null
+);
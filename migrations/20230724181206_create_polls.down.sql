@@ -0,0 +1,6 @@
+drop index poll_votes_option_id;
+drop index poll_votes_post_id_account_id;
+drop table poll_votes;
+drop index poll_options_post_id;
+drop table poll_options;
+alter table posts drop column poll_closes_at;
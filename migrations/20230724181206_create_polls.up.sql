@@ -0,0 +1,23 @@
+alter table posts add column poll_closes_at int;
+
+create table poll_options (
+    id integer primary key,
+    post_id integer not null references posts(id),
+    body text not null,
+    updated_at int not null,
+    created_at int not null
+);
+
+create index poll_options_post_id on poll_options(post_id);
+
+create table poll_votes (
+    id integer primary key,
+    post_id integer not null references posts(id),
+    option_id integer not null references poll_options(id),
+    account_id integer not null references accounts(id),
+    updated_at int not null,
+    created_at int not null
+);
+
+create unique index poll_votes_post_id_account_id on poll_votes(post_id, account_id);
+create index poll_votes_option_id on poll_votes(option_id);
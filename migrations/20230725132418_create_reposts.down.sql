@@ -0,0 +1,5 @@
+drop index reposts_post_id;
+drop index reposts_account_id_post_id;
+drop table reposts;
+drop index posts_quoted_post_id;
+alter table posts drop column quoted_post_id;
@@ -0,0 +1,14 @@
+alter table posts add column quoted_post_id integer references posts(id);
+
+create index posts_quoted_post_id on posts(quoted_post_id);
+
+create table reposts (
+    id integer primary key,
+    account_id integer not null references accounts(id),
+    post_id integer not null references posts(id),
+    updated_at int not null,
+    created_at int not null
+);
+
+create unique index reposts_account_id_post_id on reposts(account_id, post_id);
+create index reposts_post_id on reposts(post_id);
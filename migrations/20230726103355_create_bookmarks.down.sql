@@ -0,0 +1,3 @@
+drop index bookmarks_post_id;
+drop index bookmarks_account_id_post_id;
+drop table bookmarks;
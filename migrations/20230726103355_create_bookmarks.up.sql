@@ -0,0 +1,10 @@
+create table bookmarks (
+    id integer primary key,
+    account_id integer not null references accounts(id),
+    post_id integer not null references posts(id),
+    updated_at int not null,
+    created_at int not null
+);
+
+create unique index bookmarks_account_id_post_id on bookmarks(account_id, post_id);
+create index bookmarks_post_id on bookmarks(post_id);
@@ -0,0 +1,2 @@
+drop index accounts_name_skeleton;
+alter table accounts drop column name_skeleton;
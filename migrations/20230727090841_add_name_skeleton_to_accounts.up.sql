@@ -0,0 +1,3 @@
+alter table accounts add column name_skeleton text;
+
+create index accounts_name_skeleton on accounts(name_skeleton);
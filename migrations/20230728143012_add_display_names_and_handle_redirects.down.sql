@@ -0,0 +1,3 @@
+drop table handle_redirects;
+alter table accounts drop column name_changed_at;
+alter table accounts drop column display_name;
@@ -0,0 +1,13 @@
+alter table accounts add column display_name text;
+alter table accounts add column name_changed_at int;
+
+create table handle_redirects (
+    name text primary key not null,
+    name_skeleton text not null,
+    account_id integer not null references accounts(id),
+    expires_at int not null,
+    created_at int not null
+);
+
+create index handle_redirects_name_skeleton on handle_redirects(name_skeleton);
+create index handle_redirects_account_id on handle_redirects(account_id);
@@ -0,0 +1,3 @@
+alter table accounts drop column pronouns;
+alter table accounts drop column website;
+alter table accounts drop column bio;
@@ -0,0 +1,3 @@
+alter table accounts add column bio text;
+alter table accounts add column website text;
+alter table accounts add column pronouns text;
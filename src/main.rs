@@ -1,7 +1,6 @@
 #![allow(non_snake_case)]
 
 /*
-    TODO: allow rotating login code
     TODO: comments
     TODO: search
     TODO: dms
@@ -16,7 +15,12 @@ use dioxus::prelude::*;
 use dioxus_fullstack::prelude::*;
 use fermi::prelude::*;
 use justerror::Error;
-use models::{Account, Comment, HasAccount, Post, SearchResult, Session};
+use models::{
+    first_url, AccountRelations, AdminAccount, Attachment, Comment, DeletionMode, Draft,
+    HasAccount, LinkPreview, ModerationAction, NewPoll, PollOption, Post, ProfileFields,
+    PublicAccount, ReportReason, ReportResolution, ReportSummary, ReportTarget, SavedPage,
+    SearchResult, Session, SessionAccount,
+};
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
@@ -44,44 +48,129 @@ mod frontend {
 #[cfg(backend)]
 mod backend {
     use super::*;
-    use crate::models::{Comment, InsertPost, Like, Post};
+    use crate::models::{
+        Account, AccountRelations, AdminAccount, Attachment, Bookmark, Comment, DeletionMode,
+        Draft, ExportedAccount, ExportedComment, ExportedPost, ExportedSession, InsertPost, Like,
+        LinkPreview, LoginCode, ModerationAction, NewPoll, PollVote, Post, ProfileFields,
+        PublicAccount, PublishedPost, ReportReason, ReportResolution, ReportSummary, ReportTarget,
+        Repost, SavedPage, SessionAccount, DELETED_ACCOUNT_NAME,
+    };
     use axum::{
-        body::{Body, Full},
-        extract::State,
-        headers::Cookie,
+        body::{Body, Full, HttpBody, StreamBody},
+        extract::{MatchedPath, Path, State},
+        headers::{authorization::Bearer, Authorization, Cookie},
         http::{header, Request, StatusCode, Uri},
-        response::{Html, IntoResponse, Response},
+        middleware::{self, Next},
+        response::{Html, IntoResponse, Redirect, Response},
         routing::get,
-        Router, Server, TypedHeader,
+        Extension, Router, Server, TypedHeader,
     };
     use dioxus_ssr;
+    use futures_util::{stream::BoxStream, StreamExt, TryStreamExt};
+    use hmac::{Hmac, Mac};
     use mime_guess;
     use rust_embed::RustEmbed;
+    use sha2::Sha256;
     use sqlx::{
+        migrate::{Migrate, Migration},
         sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
-        SqlitePool,
+        Sqlite, SqlitePool, Transaction,
     };
     use std::collections::HashMap;
-    use std::time::{Duration, SystemTime, UNIX_EPOCH};
-    use std::{net::SocketAddr, sync::Arc};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+    use std::{
+        net::{IpAddr, SocketAddr},
+        path::PathBuf,
+        sync::Arc,
+    };
+    use tokio::io::{AsyncWrite, AsyncWriteExt};
+    use tokio_util::io::ReaderStream;
+    use tracing::Instrument;
 
     #[tokio::main]
     pub async fn main() {
-        tracing_subscriber::fmt().init();
         #[cfg(debug_assertions)]
         dioxus_hot_reload::hot_reload_init!();
-        let args: Vec<String> = std::env::args().collect();
+        let mut args: Vec<String> = std::env::args().collect();
+        let flags = Env::take_flags(&mut args);
         let arg = args.get(1).cloned().unwrap_or(String::default());
         match arg.as_str() {
             "migrate" => {
-                let env = Env::new();
-                let db = Database::new(env.database_url.clone()).await;
-                db.migrate().await.expect("Error migrating");
+                let env = Env::load(flags).unwrap_or_else(|err| exit_with(err));
+                init_tracing(env.log_format);
+                let db = Database::new(&env).await;
+                let result = match args.get(2).map(|arg| arg.as_str()) {
+                    Some("status") => db.migration_status().await.map(print_migration_status),
+                    _ => db.migrate().await,
+                };
+                if let Err(err) = result {
+                    exit_with(err);
+                }
             }
             "rollback" => {
-                let env = Env::new();
-                let db = Database::new(env.database_url.clone()).await;
-                db.rollback().await.expect("Error rolling back");
+                let options = RollbackOptions::parse(&args[2..]).unwrap_or_else(|err| {
+                    eprintln!("{err}");
+                    eprintln!("usage: rollback [--steps N | --to VERSION] [--dry-run]");
+                    std::process::exit(1);
+                });
+                let env = Env::load(flags).unwrap_or_else(|err| exit_with(err));
+                init_tracing(env.log_format);
+                let db = Database::new(&env).await;
+                let result = match db.rollback_plan(options.target).await {
+                    Ok(migrations) if options.dry_run => {
+                        for migration in migrations {
+                            println!("-- {} {}", migration.version, migration.description);
+                            println!("{}", migration.sql);
+                        }
+                        Ok(())
+                    }
+                    Ok(migrations) => db.rollback(migrations).await,
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = result {
+                    exit_with(err);
+                }
+            }
+            "admin" => {
+                let Some(name) = args.get(2) else {
+                    eprintln!("usage: admin NAME [--revoke]");
+                    std::process::exit(1);
+                };
+                let is_admin = args.get(3).map(|arg| arg.as_str()) != Some("--revoke");
+                let env = Env::load(flags).unwrap_or_else(|err| exit_with(err));
+                init_tracing(env.log_format);
+                let db = Database::new(&env).await;
+                match db.set_admin(name, is_admin).await {
+                    Ok(account) if account.is_admin => println!("{} is now an admin", account.name),
+                    Ok(account) => println!("{} is no longer an admin", account.name),
+                    Err(err) => exit_with(err),
+                }
+            }
+            "export" => {
+                let Some(name) = args.get(2) else {
+                    eprintln!("usage: export NAME [FILE]");
+                    std::process::exit(1);
+                };
+                let env = Env::load(flags).unwrap_or_else(|err| exit_with(err));
+                init_tracing(env.log_format);
+                let db = Database::new(&env).await;
+                let account = db
+                    .account_by_name(name)
+                    .await
+                    .unwrap_or_else(|err| exit_with(err));
+                let result = match args.get(3) {
+                    Some(path) => match tokio::fs::File::create(path).await {
+                        Ok(mut file) => db.export_account(account.id, &mut file).await,
+                        Err(err) => Err(err.into()),
+                    },
+                    None => {
+                        db.export_account(account.id, &mut tokio::io::stdout())
+                            .await
+                    }
+                };
+                if let Err(err) = result {
+                    exit_with(err);
+                }
             }
             "frontend" => {
                 let mut html = std::fs::read_to_string("./dist/index.html").unwrap();
@@ -99,25 +188,187 @@ mod backend {
                 }
             }
             _ => {
-                let env = Env::new();
-                let db = Database::new(env.database_url.clone()).await;
-                let _ = db.migrate().await.expect("Problem running migrations");
-                let app = routes(db);
-                let addr: SocketAddr = "127.0.0.1:9004".parse().expect("Problem parsing address");
+                let env = Env::load(flags).unwrap_or_else(|err| exit_with(err));
+                init_tracing(env.log_format);
+                let db = Database::new(&env).await;
+                if let Err(err) = db.migrate().await {
+                    exit_with(err);
+                }
+                tokio::spawn(purge_deleted_accounts(db.clone(), env.deletion_grace));
+                tokio::spawn(purge_unattached_uploads(db.clone()));
+                tokio::spawn(publish_scheduled_posts(db.clone()));
+                let addr = SocketAddr::new(env.host, env.port);
+                let drain_timeout = env.drain_timeout;
+                let app = routes(db.clone(), env);
                 println!("listening on {}", addr);
-                Server::bind(&addr)
+                let server = Server::bind(&addr)
                     .serve(app.into_make_service())
-                    .await
-                    .expect("Problem starting axum");
+                    .with_graceful_shutdown(shutdown_signal());
+                let drain = async {
+                    shutdown_signal().await;
+                    tokio::time::sleep(drain_timeout).await;
+                };
+                tokio::select! {
+                    result = server => result.expect("Problem starting axum"),
+                    _ = drain => tracing::warn!(?drain_timeout, "requests still running, stopping anyway"),
+                }
+                if let Err(err) = db.close().await {
+                    exit_with(err);
+                }
+            }
+        };
+    }
+
+    fn print_migration_status(migrations: Vec<MigrationStatus>) {
+        for migration in migrations {
+            println!(
+                "{:<16} {:<10} {:<16} {}",
+                migration.version, migration.state, migration.checksum, migration.description
+            );
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub enum RollbackTarget {
+        Steps(usize),
+        To(i64),
+    }
+
+    #[derive(Debug)]
+    pub struct RollbackOptions {
+        pub target: RollbackTarget,
+        pub dry_run: bool,
+    }
+
+    impl RollbackOptions {
+        pub fn parse(args: &[String]) -> std::result::Result<Self, String> {
+            let mut options = Self {
+                target: RollbackTarget::Steps(1),
+                dry_run: false,
+            };
+            let mut args = args.iter();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--steps" => {
+                        let steps = args.next().ok_or("--steps needs a number")?;
+                        let steps = steps
+                            .parse()
+                            .map_err(|_| format!("--steps {steps} is not a number"))?;
+                        options.target = RollbackTarget::Steps(steps);
+                    }
+                    "--to" => {
+                        let version = args.next().ok_or("--to needs a version")?;
+                        let version = version
+                            .parse()
+                            .map_err(|_| format!("--to {version} is not a version"))?;
+                        options.target = RollbackTarget::To(version);
+                    }
+                    "--dry-run" => options.dry_run = true,
+                    arg => return Err(format!("unknown rollback option {arg}")),
+                }
+            }
+            Ok(options)
+        }
+    }
+
+    fn init_tracing(log_format: LogFormat) {
+        let subscriber = tracing_subscriber::fmt();
+        match log_format {
+            LogFormat::Text => subscriber.init(),
+            LogFormat::Json => subscriber.json().init(),
+        }
+    }
+
+    /// Runs for as long as the server does, hard deleting accounts once
+    /// their grace period is up.
+    async fn purge_deleted_accounts(db: Database, grace: Duration) {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            match db.purge_deleted_accounts(grace).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!(count, "purged deleted accounts"),
+                Err(err) => tracing::error!(%err, "purging deleted accounts failed"),
+            }
+        }
+    }
+
+    /// Uploads that never made it onto a post are removed after a day.
+    async fn purge_unattached_uploads(db: Database) {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            match db
+                .purge_unattached_uploads(Duration::from_secs(24 * 60 * 60))
+                .await
+            {
+                Ok(0) => {}
+                Ok(count) => tracing::info!(count, "purged unattached uploads"),
+                Err(err) => tracing::error!(%err, "purging unattached uploads failed"),
+            }
+        }
+    }
+
+    async fn publish_scheduled_posts(db: Database) {
+        let mut interval = tokio::time::interval(Duration::from_secs(15));
+        loop {
+            interval.tick().await;
+            match db.publish_due_posts().await {
+                Ok(posts) => posts.iter().for_each(on_publish),
+                Err(err) => tracing::error!(%err, "publishing scheduled posts failed"),
             }
+        }
+    }
+
+    /// Side effects of a scheduled post going out, live updates and
+    /// notifications hang off of this once they exist.
+    fn on_publish(post: &PublishedPost) {
+        tracing::info!(
+            post_id = post.id,
+            account_id = post.account_id,
+            "scheduled post published"
+        );
+        metrics::record_scheduled_publish();
+    }
+
+    async fn shutdown_signal() {
+        let ctrl_c = async {
+            tokio::signal::ctrl_c()
+                .await
+                .expect("Problem listening for ctrl-c");
+        };
+        #[cfg(unix)]
+        let terminate = async {
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("Problem listening for SIGTERM")
+                .recv()
+                .await;
         };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+        tokio::select! {
+            _ = ctrl_c => {},
+            _ = terminate => {},
+        }
+    }
+
+    fn exit_with(err: AppError) -> ! {
+        eprintln!("{}", err.report());
+        std::process::exit(1);
     }
 
-    fn routes(db: Database) -> Router {
+    fn routes(db: Database, env: Env) -> Router {
         let dynamic_routes = Router::new()
             .route("/", get(index))
+            .route("/healthz", get(healthz))
+            .route("/readyz", get(readyz))
+            .route("/metrics", get(serve_metrics))
+            .route("/account/export", get(export_account))
+            .route("/uploads/:file", get(serve_upload))
+            .route("/profile/:name", get(profile))
             .register_server_fns_with_handler("", |func| {
                 move |State(db): State<Database>,
+                      Extension(env): Extension<Env>,
                       TypedHeader(cookie): TypedHeader<Cookie>,
                       req: Request<Body>| async move {
                     let (parts, body) = req.into_parts();
@@ -125,8 +376,14 @@ mod backend {
                     let mut server_context = DioxusServerContext::new(parts.clone());
                     let identifier = cookie.get("id").unwrap_or_default();
                     let session = db.session_by_identifer(identifier).await.ok();
+                    let span = tracing::Span::current();
+                    span.record("server_fn", parts.uri.path());
+                    if let Some(session) = &session {
+                        span.record("account_id", session.account_id);
+                    }
                     let _ = server_context.insert(session);
                     let _ = server_context.insert(db);
+                    let _ = server_context.insert(env);
                     let Some(content_type) = parts
                         .headers
                         .get("Content-Type")
@@ -135,15 +392,19 @@ mod backend {
                         return (StatusCode::INTERNAL_SERVER_ERROR, "what").into_response();
                     };
                     if content_type != "application/cbor" {
-                        (StatusCode::INTERNAL_SERVER_ERROR, "what").into_response()
-                    } else {
-                        server_fn_handler(server_context, func.clone(), parts, body)
-                            .await
-                            .into_response()
+                        return (StatusCode::INTERNAL_SERVER_ERROR, "what").into_response();
                     }
+                    let body = match read_server_fn_body(body).await {
+                        Ok(body) => body,
+                        Err(status) => return status.into_response(),
+                    };
+                    server_fn_handler(server_context, func.clone(), parts, body)
+                        .await
+                        .into_response()
                 }
             })
             .connect_hot_reload()
+            .layer(Extension(env))
             .with_state(db);
         let static_routes = Router::new().route("/assets/*file", get(serve_assets));
 
@@ -151,38 +412,129 @@ mod backend {
             .nest("", dynamic_routes)
             .nest("", static_routes)
             .fallback_service(get(not_found))
+            .layer(middleware::from_fn(trace_request))
+    }
+
+    /// Cbor writes each byte of an upload as up to two, anything past that
+    /// and a little room for the other arguments isn't a request we'd accept.
+    const MAX_SERVER_FN_BODY: usize = 2 * uploads::MAX_BYTES + 64 * 1024;
+
+    /// Server fn arguments are decoded from memory, so the body is read here
+    /// with a cap instead of buffering whatever the client sends.
+    async fn read_server_fn_body(mut body: Body) -> std::result::Result<Body, StatusCode> {
+        if body.size_hint().lower() > MAX_SERVER_FN_BODY as u64 {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+            if bytes.len() + chunk.len() > MAX_SERVER_FN_BODY {
+                return Err(StatusCode::PAYLOAD_TOO_LARGE);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(Body::from(bytes))
+    }
+
+    async fn trace_request<B>(req: Request<B>, next: Next<B>) -> Response {
+        let path = req.uri().path().to_string();
+        // routing has already happened, the layer is added with Router::layer
+        let route = req
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|matched| matched.as_str().to_string());
+        let span = tracing::info_span!(
+            "request",
+            method = %req.method(),
+            path = %path,
+            server_fn = tracing::field::Empty,
+            account_id = tracing::field::Empty,
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+        let start = Instant::now();
+        let response = next.run(req).instrument(span.clone()).await;
+        let latency = start.elapsed();
+        let status = response.status();
+        span.record("status", status.as_u16());
+        span.record("latency_ms", latency.as_millis() as u64);
+        span.in_scope(|| tracing::info!("request finished"));
+        metrics::record_request(route.as_deref(), status, latency);
+        response
     }
 
     async fn index(
         TypedHeader(cookie): TypedHeader<Cookie>,
         State(db): State<Database>,
+        Extension(env): Extension<Env>,
     ) -> Html<String> {
+        let server_props = server_props(&db, &cookie).await;
+        let index_html = Assets::get("index.html").unwrap();
+        let index_html = std::str::from_utf8(index_html.data.as_ref()).unwrap();
+        let canonical = format!("{}/", env.public_url);
+        Html(render_page(index_html, &canonical, server_props))
+    }
+
+    /// Profile links that use an old handle redirect to the current one.
+    async fn profile(
+        Path(name): Path<String>,
+        TypedHeader(cookie): TypedHeader<Cookie>,
+        State(db): State<Database>,
+        Extension(env): Extension<Env>,
+    ) -> Response {
+        let Ok(account) = db.account_by_handle(&name).await else { return AppError::NotFound.into_response() };
+        if account.name != name {
+            return Redirect::temporary(&format!("/profile/{}", account.name)).into_response();
+        }
+        let canonical = format!("{}/profile/{}", env.public_url, account.name);
+        let server_props = profile_props(&db, &cookie, account).await;
+        let index_html = Assets::get("index.html").unwrap();
+        let index_html = std::str::from_utf8(index_html.data.as_ref()).unwrap();
+        Html(render_page(index_html, &canonical, server_props)).into_response()
+    }
+
+    async fn profile_props(db: &Database, cookie: &Cookie, account: Account) -> ServerProps {
+        let mut server_props = server_props(db, cookie).await;
+        server_props.profile_fields = db.profile_fields(account.id).await.ok();
+        server_props.view = View::Profile(account.into());
+        server_props
+    }
+
+    async fn server_props(db: &Database, cookie: &Cookie) -> ServerProps {
         let identifier = cookie.get("id").unwrap_or_default();
         let session = db.session_by_identifer(identifier).await.ok();
+        if let Some(session) = &session {
+            tracing::Span::current().record("account_id", session.account_id);
+        }
         let account = db
             .account_by_id(session.unwrap_or_default().account_id)
             .await
             .ok();
         let posts = db.posts(account.as_ref()).await.unwrap_or_default();
         let view = View::default();
-        let server_props = ServerProps {
-            account,
+        ServerProps {
+            account: account.map(SessionAccount::from),
             posts,
             view,
-        };
+            profile_fields: None,
+        }
+    }
+
+    /// `canonical` is the page's address under `PUBLIC_URL`, so links to it
+    /// point at one place whatever host it was reached through.
+    fn render_page(index_html: &str, canonical: &str, server_props: ServerProps) -> String {
         let mut vdom = VirtualDom::new_with_props(Router, server_props.clone());
         let _ = vdom.rebuild();
         let app = dioxus_ssr::pre_render(&vdom);
-        let index_html = Assets::get("index.html").unwrap();
-        let index_html = std::str::from_utf8(index_html.data.as_ref()).unwrap();
-        let index_html = index_html.replace("<!-- app -->", &app);
-        let index_html = index_html.replace(
+        let index_html = index_html
+            .replace("<!-- app -->", &app)
+            .replace("<!-- canonical -->", &canonical.replace('"', "&quot;"));
+        index_html.replace(
             "<!-- props -->",
             &serde_json::to_string(&server_props)
                 .unwrap()
                 .replace("\"", "&quot;"),
-        );
-        Html(index_html)
+        )
     }
 
     async fn serve_assets(uri: Uri) -> impl IntoResponse {
@@ -193,15 +545,136 @@ mod backend {
         StaticFile(path)
     }
 
+    /// Upload names are random and never reused so they can be cached forever.
+    async fn serve_upload(State(db): State<Database>, Path(file): Path<String>) -> Response {
+        let Some(path) = db.upload_path(&file) else { return AppError::NotFound.into_response() };
+        let Ok(bytes) = tokio::fs::read(path).await else { return AppError::NotFound.into_response() };
+        let mime = mime_guess::from_path(&file).first_or_octet_stream();
+        (
+            [
+                (header::CONTENT_TYPE, mime.to_string()),
+                (
+                    header::CACHE_CONTROL,
+                    "public, max-age=31536000, immutable".to_string(),
+                ),
+            ],
+            bytes,
+        )
+            .into_response()
+    }
+
+    async fn healthz(State(db): State<Database>) -> impl IntoResponse {
+        match db.ping().await {
+            Ok(_) => (StatusCode::OK, "ok"),
+            Err(_) => (StatusCode::SERVICE_UNAVAILABLE, "database unavailable"),
+        }
+    }
+
+    async fn readyz(State(db): State<Database>) -> impl IntoResponse {
+        match db.migration_status().await {
+            Ok(migrations)
+                if migrations
+                    .iter()
+                    .all(|m| m.state == MigrationState::Applied) =>
+            {
+                (StatusCode::OK, "ready")
+            }
+            Ok(_) => (StatusCode::SERVICE_UNAVAILABLE, "migrations not applied"),
+            Err(_) => (StatusCode::SERVICE_UNAVAILABLE, "database unavailable"),
+        }
+    }
+
+    /// Off unless `METRICS_TOKEN` is set, scrapers send it as a bearer token.
+    async fn serve_metrics(
+        State(db): State<Database>,
+        Extension(env): Extension<Env>,
+        authorization: Option<TypedHeader<Authorization<Bearer>>>,
+    ) -> Response {
+        let Some(token) = env.metrics_token.as_deref() else { return AppError::NotFound.into_response() };
+        let authorized = authorization
+            .map_or(false, |TypedHeader(auth)| tokens_match(auth.token(), token));
+        if !authorized {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+        (
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+            metrics::render(db.pool_stats()),
+        )
+            .into_response()
+    }
+
+    /// Looks at every byte so how long it takes doesn't say how much matched.
+    fn tokens_match(given: &str, expected: &str) -> bool {
+        given.len() == expected.len()
+            && given
+                .bytes()
+                .zip(expected.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+
+    /// Streams the logged in account's data as a json download.
+    async fn export_account(
+        State(db): State<Database>,
+        TypedHeader(cookie): TypedHeader<Cookie>,
+    ) -> Response {
+        let identifier = cookie.get("id").unwrap_or_default();
+        let Ok(session) = db.session_by_identifer(identifier).await else {
+            return (
+                StatusCode::UNAUTHORIZED,
+                "You need to be logged in to do that",
+            )
+                .into_response();
+        };
+        let account = match db.account_by_id(session.account_id).await {
+            Ok(account) => account,
+            Err(err) => return err.into_response(),
+        };
+        let (reader, mut writer) = tokio::io::duplex(64 * 1024);
+        let (done, finished) = tokio::sync::oneshot::channel();
+        tokio::spawn(
+            async move {
+                let result = db.export_account(account.id, &mut writer).await;
+                if let Err(err) = &result {
+                    tracing::error!(%err, "account export failed");
+                }
+                drop(writer);
+                let _ = done.send(result.is_ok());
+            }
+            .in_current_span(),
+        );
+        // the 200 is already out by the time an export can fail, ending the
+        // body with an error aborts the download instead of finishing it
+        // as a truncated file
+        let outcome = futures_util::stream::once(finished).filter_map(|finished| async move {
+            match finished {
+                Ok(true) => None,
+                _ => Some(Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "account export failed",
+                ))),
+            }
+        });
+        let disposition = format!(r#"attachment; filename="yallpost-{}.json""#, account.name);
+        (
+            [
+                (header::CONTENT_TYPE, "application/json".to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            StreamBody::new(ReaderStream::new(reader).chain(outcome)),
+        )
+            .into_response()
+    }
+
     async fn not_found() -> impl IntoResponse {
         AppError::NotFound
     }
 
-    pub fn set_cookie(session: Session) -> String {
-        #[allow(unused_variables)]
-        let secure = "Secure;";
-        #[cfg(debug_assertions)]
-        let secure = "";
+    pub fn set_cookie(session: Session, secure: bool) -> String {
+        let secure = match secure {
+            true => "Secure;",
+            false => "",
+        };
 
         format!(
             "{}={}; HttpOnly; SameSite=Lax; Path=/; Max-Age=2629746; {}",
@@ -213,7 +686,7 @@ mod backend {
         fn from(value: sqlx::Error) -> Self {
             match value {
                 sqlx::Error::RowNotFound => AppError::NotFound,
-                sqlx::Error::Migrate(_) => AppError::Migrate,
+                sqlx::Error::Migrate(err) => (*err).into(),
                 sqlx::Error::Database(err) => {
                     if err.is_unique_violation() {
                         AppError::DatabaseUniqueIndex
@@ -226,16 +699,57 @@ mod backend {
         }
     }
 
+    impl From<std::io::Error> for AppError {
+        fn from(value: std::io::Error) -> Self {
+            AppError::Io(value.to_string())
+        }
+    }
+
+    impl From<serde_json::Error> for AppError {
+        fn from(value: serde_json::Error) -> Self {
+            AppError::Io(value.to_string())
+        }
+    }
+
     impl From<sqlx::migrate::MigrateError> for AppError {
         fn from(value: sqlx::migrate::MigrateError) -> Self {
             match value {
-                sqlx::migrate::MigrateError::Execute(_) => todo!(),
-                sqlx::migrate::MigrateError::Source(_) => todo!(),
-                sqlx::migrate::MigrateError::VersionMissing(_) => todo!(),
-                sqlx::migrate::MigrateError::VersionMismatch(_) => todo!(),
-                sqlx::migrate::MigrateError::InvalidMixReversibleAndSimple => todo!(),
-                sqlx::migrate::MigrateError::Dirty(_) => todo!(),
-                _ => todo!(),
+                sqlx::migrate::MigrateError::VersionMissing(version) => {
+                    AppError::MigrationMissing(version)
+                }
+                sqlx::migrate::MigrateError::VersionMismatch(version) => {
+                    AppError::MigrationChecksumMismatch(version)
+                }
+                sqlx::migrate::MigrateError::Dirty(version) => AppError::MigrationDirty(version),
+                err => AppError::Migrate(err.to_string()),
+            }
+        }
+    }
+
+    impl AppError {
+        /// What gets printed when the server can't start, says what to do next.
+        pub fn report(&self) -> String {
+            match self {
+                AppError::Migrate(err) => format!("Error migrating: {err}"),
+                AppError::MigrationMissing(version) => format!(
+                    "Error migrating: migration {version} was applied to the database but is missing from ./migrations"
+                ),
+                AppError::MigrationChecksumMismatch(version) => format!(
+                    "Error migrating: migration {version} was changed after it was applied, restore the original file or roll it back first"
+                ),
+                AppError::MigrationDirty(version) => format!(
+                    "Error migrating: migration {version} only partially ran, fix the database by hand then delete version {version} from _sqlx_migrations"
+                ),
+                AppError::Rollback => "Error rolling back: there is nothing to roll back".to_string(),
+                AppError::Env(errors) => format!(
+                    "Error in configuration, set these in the environment or in .env:\n  {}",
+                    errors.join("\n  ")
+                ),
+                AppError::RollbackMissing(version) => format!(
+                    "Error rolling back: migration {version} has no .down.sql, nothing was rolled back"
+                ),
+                AppError::Io(err) => format!("Error reading or writing: {err}"),
+                err => format!("Error: {err}"),
             }
         }
     }
@@ -289,40 +803,293 @@ mod backend {
         }
     }
 
+    type HmacSha256 = Hmac<Sha256>;
+
+    const LOGIN_CODE_PREFIX_LEN: usize = 6;
+
+    #[derive(Clone)]
+    pub struct LoginCodeKey(Arc<[u8]>);
+
+    impl LoginCodeKey {
+        pub fn new(key: &str) -> Self {
+            Self(key.as_bytes().into())
+        }
+
+        fn mac(&self) -> HmacSha256 {
+            HmacSha256::new_from_slice(&self.0).expect("hmac takes keys of any size")
+        }
+
+        pub fn hash(&self, login_code: &str) -> String {
+            let mut mac = self.mac();
+            mac.update(login_code.as_bytes());
+            hex::encode(mac.finalize().into_bytes())
+        }
+
+        pub fn verify(&self, login_code: &str, hash: &str) -> bool {
+            let Ok(hash) = hex::decode(hash) else { return false; };
+            let mut mac = self.mac();
+            mac.update(login_code.as_bytes());
+            mac.verify_slice(&hash).is_ok()
+        }
+    }
+
+    impl std::fmt::Debug for LoginCodeKey {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("LoginCodeKey(..)")
+        }
+    }
+
+    fn login_code_prefix(login_code: &str) -> String {
+        login_code.chars().take(LOGIN_CODE_PREFIX_LEN).collect()
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum MigrationState {
+        Applied,
+        Pending,
+        Dirty,
+        ChecksumMismatch,
+        Missing,
+    }
+
+    impl Display for MigrationState {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let result = match self {
+                MigrationState::Applied => "applied",
+                MigrationState::Pending => "pending",
+                MigrationState::Dirty => "dirty",
+                MigrationState::ChecksumMismatch => "changed",
+                MigrationState::Missing => "missing",
+            };
+            f.pad(result)
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct MigrationStatus {
+        pub version: i64,
+        pub description: String,
+        pub checksum: String,
+        pub state: MigrationState,
+    }
+
     #[derive(Debug, Clone)]
     pub struct Database {
         pool: SqlitePool,
+        login_code_key: LoginCodeKey,
+        report_threshold: i64,
+        uploads_dir: PathBuf,
+        previews: Arc<dyn previews::PreviewFetcher>,
     }
 
     type Result<T> = std::result::Result<T, AppError>;
 
+    /// Which posts `Database::select_posts` reads.
+    enum PostFilter {
+        Feed,
+        Id(i64),
+        /// Bookmarks older than the `before` bookmark id, newest first.
+        Saved {
+            before: i64,
+        },
+    }
+
     impl Database {
-        pub async fn new(filename: String) -> Self {
+        pub async fn new(env: &Env) -> Self {
             Self {
-                pool: Self::pool(&filename).await,
+                pool: Self::pool(&env.database_url, env.pool_size).await,
+                login_code_key: LoginCodeKey::new(&env.login_code_key),
+                report_threshold: env.report_threshold,
+                uploads_dir: env.uploads_dir.clone(),
+                previews: Arc::new(previews::HttpFetcher::default()),
             }
         }
 
         pub async fn migrate(&self) -> Result<()> {
             sqlx::migrate!().run(&self.pool).await?;
+            self.hash_plain_login_codes().await?;
+            self.fill_name_skeletons().await?;
+            Ok(())
+        }
+
+        /// Accounts from before skeletons were stored get theirs here.
+        /// Older names that already collide keep working, the check only
+        /// applies to new names.
+        async fn fill_name_skeletons(&self) -> Result<()> {
+            let accounts = sqlx::query!(
+                "select id, name from accounts where name_skeleton is null and name != ?",
+                DELETED_ACCOUNT_NAME
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            for account in accounts {
+                let skeleton = crate::name_skeleton(&account.name);
+                sqlx::query!(
+                    "update accounts set name_skeleton = ? where id = ?",
+                    skeleton,
+                    account.id
+                )
+                .execute(&self.pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        /// Login codes from before they were hashed are carried over
+        /// by the migration as `plain:<code>`, this hashes them in place.
+        async fn hash_plain_login_codes(&self) -> Result<()> {
+            let login_codes = sqlx::query_as!(
+                LoginCode,
+                "select id, login_code_hash from accounts where login_code_hash like 'plain:%'"
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            for login_code in login_codes {
+                let plain = login_code.login_code_hash.trim_start_matches("plain:");
+                let hash = self.login_code_key.hash(plain);
+                sqlx::query!(
+                    "update accounts set login_code_hash = ? where id = ?",
+                    hash,
+                    login_code.id
+                )
+                .execute(&self.pool)
+                .await?;
+            }
             Ok(())
         }
 
-        pub async fn rollback(&self) -> Result<()> {
-            let migrations = sqlx::migrate!()
+        /// Only reads, so `/readyz` can call it without creating the
+        /// migrations table on a database that hasn't been migrated.
+        pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>> {
+            let mut conn = self.pool.acquire().await?;
+            let has_table = sqlx::query(
+                "select name from sqlite_master where type = 'table' and name = '_sqlx_migrations'",
+            )
+            .fetch_optional(&mut *conn)
+            .await?
+            .is_some();
+            let (applied, dirty) = match has_table {
+                true => (
+                    conn.list_applied_migrations().await?,
+                    conn.dirty_version().await?,
+                ),
+                false => (vec![], None),
+            };
+            let migrator = sqlx::migrate!();
+            let mut statuses = migrator
                 .migrations
                 .iter()
-                .filter(|m| m.migration_type.is_down_migration());
-            let Some(migration) = migrations.last() else { return Err(AppError::Rollback); };
-            if !migration.migration_type.is_down_migration() {
+                .filter(|m| !m.migration_type.is_down_migration())
+                .map(|migration| {
+                    let applied = applied.iter().find(|a| a.version == migration.version);
+                    let state = match applied {
+                        _ if dirty == Some(migration.version) => MigrationState::Dirty,
+                        Some(a) if a.checksum != migration.checksum => {
+                            MigrationState::ChecksumMismatch
+                        }
+                        Some(_) => MigrationState::Applied,
+                        None => MigrationState::Pending,
+                    };
+                    let checksum = applied
+                        .map(|a| a.checksum.as_ref())
+                        .unwrap_or(migration.checksum.as_ref());
+                    MigrationStatus {
+                        version: migration.version,
+                        description: migration.description.to_string(),
+                        checksum: hex::encode(checksum).chars().take(16).collect(),
+                        state,
+                    }
+                })
+                .collect::<Vec<_>>();
+            for applied in applied {
+                if !statuses.iter().any(|s| s.version == applied.version) {
+                    statuses.push(MigrationStatus {
+                        version: applied.version,
+                        description: String::default(),
+                        checksum: hex::encode(applied.checksum).chars().take(16).collect(),
+                        state: MigrationState::Missing,
+                    });
+                }
+            }
+            statuses.sort_by_key(|s| s.version);
+            Ok(statuses)
+        }
+
+        /// The down migrations to run, newest first, for what's actually applied.
+        pub async fn rollback_plan(&self, target: RollbackTarget) -> Result<Vec<Migration>> {
+            let mut conn = self.pool.acquire().await?;
+            conn.ensure_migrations_table().await?;
+            let mut versions = conn
+                .list_applied_migrations()
+                .await?
+                .into_iter()
+                .map(|a| a.version)
+                .collect::<Vec<_>>();
+            versions.sort_by(|a, b| b.cmp(a));
+            let versions = match target {
+                RollbackTarget::Steps(steps) => versions.into_iter().take(steps).collect(),
+                RollbackTarget::To(version) => versions
+                    .into_iter()
+                    .filter(|v| *v > version)
+                    .collect::<Vec<_>>(),
+            };
+            if versions.is_empty() {
                 return Err(AppError::Rollback);
             }
-            let version = migration.version;
-            sqlx::query(&migration.sql).execute(&self.pool).await?;
-            sqlx::query("delete from _sqlx_migrations where version = ?")
-                .bind(version)
+            let migrator = sqlx::migrate!();
+            versions
+                .into_iter()
+                .map(|version| {
+                    migrator
+                        .migrations
+                        .iter()
+                        .find(|m| m.version == version && m.migration_type.is_down_migration())
+                        .cloned()
+                        .ok_or(AppError::RollbackMissing(version))
+                })
+                .collect()
+        }
+
+        /// Each down migration runs in its own transaction,
+        /// a failure leaves the ones before it rolled back.
+        pub async fn rollback(&self, migrations: Vec<Migration>) -> Result<()> {
+            for migration in migrations {
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(&migration.sql).execute(&mut *tx).await?;
+                sqlx::query!(
+                    "delete from _sqlx_migrations where version = ?",
+                    migration.version
+                )
+                .execute(&mut *tx)
+                .await?;
+                tx.commit().await?;
+                println!(
+                    "rolled back {} {}",
+                    migration.version, migration.description
+                );
+            }
+            Ok(())
+        }
+
+        pub fn pool_stats(&self) -> metrics::PoolStats {
+            metrics::PoolStats {
+                size: self.pool.size(),
+                idle: self.pool.num_idle(),
+                max: self.pool.options().get_max_connections(),
+            }
+        }
+
+        pub async fn ping(&self) -> Result<()> {
+            sqlx::query("select 1").execute(&self.pool).await?;
+            Ok(())
+        }
+
+        /// Folds the WAL back into the database file before the pool goes away.
+        pub async fn close(&self) -> Result<()> {
+            sqlx::query("pragma wal_checkpoint(truncate)")
                 .execute(&self.pool)
                 .await?;
+            self.pool.close().await;
             Ok(())
         }
 
@@ -335,9 +1102,9 @@ mod backend {
                 .busy_timeout(Duration::from_secs(30))
         }
 
-        async fn pool(filename: &str) -> SqlitePool {
+        async fn pool(filename: &str, pool_size: u32) -> SqlitePool {
             SqlitePoolOptions::new()
-                .max_connections(5)
+                .max_connections(pool_size)
                 .connect_with(Self::connection_options(filename))
                 .await
                 .unwrap()
@@ -350,14 +1117,76 @@ mod backend {
                 .as_secs_f64()
         }
 
+        /// New accounts start with a login code nobody has seen,
+        /// `rotate_login_code` is what hands one out.
         pub async fn insert_account(&self, name: String) -> Result<Account> {
-            let token = nanoid::nanoid!();
+            let _timer = metrics::query_timer("insert_account");
+            let login_code = nanoid::nanoid!();
+            let prefix = login_code_prefix(&login_code);
+            let hash = self.login_code_key.hash(&login_code);
             let now = Self::now();
-            let account= sqlx::query_as!(Account, "insert into accounts (name, login_code, updated_at, created_at) values (?, ?, ?, ?) returning *", name, token, now, now).fetch_one(&self.pool).await?;
-            Ok(account)
+            let skeleton = crate::name_skeleton(&name);
+            // a lookalike of an existing name counts as taken
+            let account = sqlx::query_as!(
+                Account,
+                r#"insert into accounts (name, name_skeleton, login_code_prefix, login_code_hash, updated_at, created_at) select ?, ?, ?, ?, ?, ? where not exists (select 1 from accounts where name_skeleton = ?) and not exists (select 1 from handle_redirects where name_skeleton = ? and expires_at > ?) returning id, name, display_name, is_admin as "is_admin: bool", updated_at, created_at"#,
+                name,
+                skeleton,
+                prefix,
+                hash,
+                now,
+                now,
+                skeleton,
+                skeleton,
+                now
+            )
+            .fetch_optional(&self.pool)
+            .await?;
+            account.ok_or(AppError::DatabaseUniqueIndex)
+        }
+
+        pub async fn name_available(&self, name: &str) -> Result<bool> {
+            let _timer = metrics::query_timer("name_available");
+            let skeleton = crate::name_skeleton(name);
+            let now = Self::now();
+            let row = sqlx::query!(
+                r#"
+                    select count(*) as "count!: i64" from (
+                        select id from accounts where name = ? or name_skeleton = ?
+                        union all
+                        select account_id from handle_redirects where name_skeleton = ? and expires_at > ?
+                    )
+                "#,
+                name,
+                skeleton,
+                skeleton,
+                now
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(row.count == 0)
+        }
+
+        pub async fn rotate_login_code(&self, account_id: i64) -> Result<String> {
+            let _timer = metrics::query_timer("rotate_login_code");
+            let login_code = nanoid::nanoid!();
+            let prefix = login_code_prefix(&login_code);
+            let hash = self.login_code_key.hash(&login_code);
+            let now = Self::now();
+            sqlx::query!(
+                "update accounts set login_code_prefix = ?, login_code_hash = ?, updated_at = ? where id = ?",
+                prefix,
+                hash,
+                now,
+                account_id
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(login_code)
         }
 
         pub async fn insert_session(&self, account_id: i64) -> Result<Session> {
+            let _timer = metrics::query_timer("insert_session");
             let identifier = nanoid::nanoid!();
             let now = Self::now();
             let session = sqlx::query_as!(Session, "insert into sessions (identifier, account_id, updated_at, created_at) values (?, ?, ?, ?) returning *", identifier, account_id, now, now).fetch_one(&self.pool).await?;
@@ -365,36 +1194,54 @@ mod backend {
         }
 
         pub async fn account_by_id(&self, id: i64) -> Result<Account> {
-            let account =
-                sqlx::query_as!(Account, "select * from accounts where id = ? limit 1", id)
-                    .fetch_one(&self.pool)
-                    .await?;
+            let _timer = metrics::query_timer("account_by_id");
+            let account = sqlx::query_as!(
+                Account,
+                r#"select id, name, display_name, is_admin as "is_admin: bool", updated_at, created_at from accounts where id = ? limit 1"#,
+                id
+            )
+            .fetch_one(&self.pool)
+            .await?;
             Ok(account)
         }
 
         pub async fn session_by_identifer(&self, identifier: &str) -> Result<Session> {
+            let _timer = metrics::query_timer("session_by_identifer");
             let session = sqlx::query_as!(
                 Session,
-                "select * from sessions where identifier = ? limit 1",
-                identifier
-            )
+                r#"
+                    select sessions.*
+                    from sessions
+                    join accounts on accounts.id = sessions.account_id
+                    where sessions.identifier = ? and accounts.suspended_at is null
+                    limit 1
+                "#,
+                identifier
+            )
             .fetch_one(&self.pool)
             .await?;
             Ok(session)
         }
 
         pub async fn account_by_login_code(&self, login_code: String) -> Result<Account> {
-            let account = sqlx::query_as!(
-                Account,
-                "select * from accounts where login_code = ? limit 1",
-                login_code
+            let _timer = metrics::query_timer("account_by_login_code");
+            let prefix = login_code_prefix(&login_code);
+            let login_codes = sqlx::query_as!(
+                LoginCode,
+                "select id, login_code_hash from accounts where login_code_prefix = ? and suspended_at is null",
+                prefix
             )
-            .fetch_one(&self.pool)
+            .fetch_all(&self.pool)
             .await?;
-            Ok(account)
+            let Some(login_code) = login_codes
+                .into_iter()
+                .find(|lc| self.login_code_key.verify(&login_code, &lc.login_code_hash))
+                else { return Err(AppError::NotFound); };
+            self.account_by_id(login_code.id).await
         }
 
         pub async fn delete_session_by_identifier(&self, identifier: &str) -> Result<Session> {
+            let _timer = metrics::query_timer("delete_session_by_identifier");
             let session = sqlx::query_as!(
                 Session,
                 "delete from sessions where identifier = ? returning *",
@@ -405,48 +1252,659 @@ mod backend {
             Ok(session)
         }
 
-        pub async fn delete_account_by_id(&self, id: i64) -> Result<Account> {
-            let account =
-                sqlx::query_as!(Account, "delete from accounts where id = ? returning *", id)
-                    .fetch_one(&self.pool)
+        /// Hides the account and its content and logs it out everywhere,
+        /// `purge_deleted_accounts` does the actual deleting later on.
+        pub async fn request_account_deletion(&self, id: i64, mode: DeletionMode) -> Result<()> {
+            let _timer = metrics::query_timer("request_account_deletion");
+            let now = Self::now();
+            let mode = mode.as_str();
+            let mut tx = self.pool.begin().await?;
+            sqlx::query!(
+                "update accounts set deletion_requested_at = ?, deletion_mode = ?, updated_at = ? where id = ?",
+                now,
+                mode,
+                now,
+                id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!("delete from sessions where account_id = ?", id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            Ok(())
+        }
+
+        /// Returns true when the account was pending deletion. Once the
+        /// grace period is up the account is as good as gone, even when the
+        /// purge hasn't got to it yet, so it comes back as `NotFound`.
+        pub async fn restore_account(&self, id: i64, grace: Duration) -> Result<bool> {
+            let _timer = metrics::query_timer("restore_account");
+            let now = Self::now();
+            let before = now - grace.as_secs_f64();
+            let account = sqlx::query!(
+                "select deletion_requested_at from accounts where id = ?",
+                id
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            match account.deletion_requested_at {
+                None => return Ok(false),
+                Some(requested_at) if (requested_at as f64) < before => {
+                    return Err(AppError::NotFound)
+                }
+                Some(_) => {}
+            }
+            let result = sqlx::query!(
+                "update accounts set deletion_requested_at = null, deletion_mode = null, updated_at = ? where id = ? and deletion_requested_at >= ?",
+                now,
+                id,
+                before
+            )
+            .execute(&self.pool)
+            .await?;
+            match result.rows_affected() {
+                0 => Err(AppError::NotFound),
+                _ => Ok(true),
+            }
+        }
+
+        /// Hard deletes accounts whose deletion was requested longer than
+        /// `grace` ago, returns how many were deleted. One account failing
+        /// doesn't hold up the rest, it gets tried again next time.
+        pub async fn purge_deleted_accounts(&self, grace: Duration) -> Result<usize> {
+            let _timer = metrics::query_timer("purge_deleted_accounts");
+            let before = Self::now() - grace.as_secs_f64();
+            let accounts = sqlx::query!(
+                r#"select id, deletion_mode as "deletion_mode!: String" from accounts where deletion_requested_at < ?"#,
+                before
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            let mut purged = 0;
+            for account in &accounts {
+                let mode = account
+                    .deletion_mode
+                    .parse()
+                    .unwrap_or(DeletionMode::Remove);
+                match self.delete_account(account.id, mode).await {
+                    Ok(_) => purged += 1,
+                    Err(err) => {
+                        tracing::error!(%err, account_id = account.id, "purging account failed")
+                    }
+                }
+            }
+            Ok(purged)
+        }
+
+        /// Deletes an account and everything that points at it in one
+        /// transaction. Anonymising hands posts and comments over to the
+        /// `[deleted]` account instead of removing them.
+        pub async fn delete_account(&self, id: i64, mode: DeletionMode) -> Result<Account> {
+            let _timer = metrics::query_timer("delete_account");
+            let mut tx = self.pool.begin().await?;
+            match mode {
+                DeletionMode::Remove => {
+                    // detached here, removed with the rest of their uploads below
+                    sqlx::query!(
+                        "update attachments set post_id = null where account_id = ?",
+                        id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query!(
+                        "delete from reports where target_kind = 'post' and target_id in (select id from posts where account_id = ?)",
+                        id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query!(
+                        "delete from reports where target_kind = 'comment' and target_id in (select comments.id from comments join posts on posts.id = comments.post_id where comments.account_id = ? or posts.account_id = ?)",
+                        id,
+                        id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query!(
+                        "delete from likes where account_id = ? or post_id in (select id from posts where account_id = ?)",
+                        id,
+                        id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query!(
+                        "delete from poll_votes where account_id = ? or post_id in (select id from posts where account_id = ?)",
+                        id,
+                        id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query!(
+                        "delete from poll_options where post_id in (select id from posts where account_id = ?)",
+                        id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query!(
+                        "delete from reposts where account_id = ? or post_id in (select id from posts where account_id = ?)",
+                        id,
+                        id
+                    )
+                    .execute(&mut *tx)
                     .await?;
+                    sqlx::query!(
+                        "delete from bookmarks where account_id = ? or post_id in (select id from posts where account_id = ?)",
+                        id,
+                        id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query!(
+                        "update posts set quoted_post_id = null where quoted_post_id in (select id from posts where account_id = ?)",
+                        id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query!(
+                        "delete from comments where account_id = ? or post_id in (select id from posts where account_id = ?)",
+                        id,
+                        id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query!("delete from posts where account_id = ?", id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                DeletionMode::Anonymise => {
+                    let deleted_id = Self::deleted_account_id(&mut tx).await?;
+                    sqlx::query!(
+                        "update attachments set account_id = ? where account_id = ? and post_id is not null",
+                        deleted_id,
+                        id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query!("delete from likes where account_id = ?", id)
+                        .execute(&mut *tx)
+                        .await?;
+                    sqlx::query!("delete from poll_votes where account_id = ?", id)
+                        .execute(&mut *tx)
+                        .await?;
+                    sqlx::query!("delete from reposts where account_id = ?", id)
+                        .execute(&mut *tx)
+                        .await?;
+                    sqlx::query!("delete from bookmarks where account_id = ?", id)
+                        .execute(&mut *tx)
+                        .await?;
+                    sqlx::query!(
+                        "update comments set account_id = ? where account_id = ?",
+                        deleted_id,
+                        id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query!(
+                        "update posts set account_id = ? where account_id = ?",
+                        deleted_id,
+                        id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+            sqlx::query!("delete from reports where reporter_id = ?", id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query!(
+                "delete from blocks where blocker_id = ? or blocked_id = ?",
+                id,
+                id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "delete from mutes where muter_id = ? or muted_id = ?",
+                id,
+                id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!("delete from drafts where account_id = ?", id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query!("delete from handle_redirects where account_id = ?", id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query!("delete from sessions where account_id = ?", id)
+                .execute(&mut *tx)
+                .await?;
+            let filenames = sqlx::query!(
+                "delete from attachments where account_id = ? returning filename",
+                id
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            let account = sqlx::query_as!(
+                Account,
+                r#"delete from accounts where id = ? returning id, name, display_name, is_admin as "is_admin: bool", updated_at, created_at"#,
+                id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            let filenames: Vec<String> = filenames.into_iter().map(|row| row.filename).collect();
+            self.remove_uploads(&filenames).await;
             Ok(account)
         }
 
-        pub async fn insert_post(&self, body: String, current_account: Account) -> Result<Post> {
+        /// The account anonymised posts belong to, it's suspended and
+        /// has no usable login code so nobody can log in as it.
+        async fn deleted_account_id(tx: &mut Transaction<'_, Sqlite>) -> Result<i64> {
+            let existing = sqlx::query!(
+                "select id from accounts where name = ?",
+                DELETED_ACCOUNT_NAME
+            )
+            .fetch_optional(&mut **tx)
+            .await?;
+            if let Some(row) = existing {
+                return Ok(row.id);
+            }
+            let now = Self::now();
+            let row = sqlx::query!(
+                "insert into accounts (name, login_code_prefix, login_code_hash, suspended_at, updated_at, created_at) values (?, '', 'deleted', ?, ?, ?) returning id",
+                DELETED_ACCOUNT_NAME,
+                now,
+                now,
+                now
+            )
+            .fetch_one(&mut **tx)
+            .await?;
+            Ok(row.id)
+        }
+
+        pub async fn insert_post(
+            &self,
+            body: String,
+            current_account: Account,
+            publish_at: Option<i64>,
+            quoted_post_id: Option<i64>,
+        ) -> Result<Post> {
+            self.insert_post_with(body, current_account, publish_at, quoted_post_id, None, &[])
+                .await
+        }
+
+        /// Writes a post with its poll and attachments in one transaction,
+        /// a failure partway through leaves no half built post behind.
+        pub async fn insert_post_with(
+            &self,
+            body: String,
+            current_account: Account,
+            publish_at: Option<i64>,
+            quoted_post_id: Option<i64>,
+            poll: Option<&NewPoll>,
+            attachment_ids: &[i64],
+        ) -> Result<Post> {
+            let _timer = metrics::query_timer("insert_post");
+            if let Some(quoted_post_id) = quoted_post_id {
+                self.ensure_can_interact(quoted_post_id, current_account.id)
+                    .await?;
+            }
             let now = Self::now();
-            let rows = sqlx::query_as!(
+            let mut tx = self.pool.begin().await?;
+            let row = sqlx::query_as!(
                 InsertPost,
-                "insert into posts (body, account_id, created_at, updated_at) values (?, ?, ?, ?) returning id",
+                "insert into posts (body, account_id, publish_at, quoted_post_id, created_at, updated_at) values (?, ?, ?, ?, ?, ?) returning id",
                 body,
                 current_account.id,
+                publish_at,
+                quoted_post_id,
+                now,
+                now
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if let Some(poll) = poll {
+                // polls on scheduled posts open when the post is published
+                let opens_at = publish_at.unwrap_or(now as i64);
+                Self::insert_poll(&mut tx, row.id, poll, opens_at + poll.closes_in).await?;
+            }
+            Self::attach(&mut tx, current_account.id, row.id, attachment_ids).await?;
+            tx.commit().await?;
+            self.post_by_id(row.id, Some(current_account)).await
+        }
+
+        /// Stores an upload that isn't on a post yet, `attach` puts it on one.
+        pub async fn insert_attachment(
+            &self,
+            account_id: i64,
+            bytes: Vec<u8>,
+        ) -> Result<Attachment> {
+            let _timer = metrics::query_timer("insert_attachment");
+            let dir = self.uploads_dir.clone();
+            let processed = tokio::task::spawn_blocking(move || uploads::save(&dir, &bytes))
+                .await
+                .map_err(|err| AppError::Io(err.to_string()))??;
+            let now = Self::now();
+            let width = processed.width as i64;
+            let height = processed.height as i64;
+            let attachment = sqlx::query_as!(
+                Attachment,
+                "insert into attachments (account_id, filename, width, height, updated_at, created_at) values (?, ?, ?, ?, ?, ?) returning *",
+                account_id,
+                processed.filename,
+                width,
+                height,
+                now,
+                now
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(attachment)
+        }
+
+        /// Only the uploader's unattached images can go on a post.
+        async fn attach(
+            tx: &mut Transaction<'_, Sqlite>,
+            account_id: i64,
+            post_id: i64,
+            ids: &[i64],
+        ) -> Result<()> {
+            for id in ids.iter().take(crate::ATTACHMENTS_PER_POST) {
+                sqlx::query!(
+                    "update attachments set post_id = ? where id = ? and account_id = ? and post_id is null",
+                    post_id,
+                    id,
+                    account_id
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+            Ok(())
+        }
+
+        /// Options keep the order they're given in, their ids go up with it.
+        async fn insert_poll(
+            tx: &mut Transaction<'_, Sqlite>,
+            post_id: i64,
+            poll: &NewPoll,
+            closes_at: i64,
+        ) -> Result<()> {
+            let now = Self::now();
+            sqlx::query!(
+                "update posts set poll_closes_at = ? where id = ?",
+                closes_at,
+                post_id
+            )
+            .execute(&mut **tx)
+            .await?;
+            for option in &poll.options {
+                let body = option.trim();
+                sqlx::query!(
+                    "insert into poll_options (post_id, body, updated_at, created_at) values (?, ?, ?, ?)",
+                    post_id,
+                    body,
+                    now,
+                    now
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+            Ok(())
+        }
+
+        /// One vote per account per poll, the unique index turns a second
+        /// one into `DatabaseUniqueIndex`.
+        pub async fn vote(&self, account_id: i64, post_id: i64, option_id: i64) -> Result<()> {
+            let _timer = metrics::query_timer("vote");
+            self.ensure_can_interact(post_id, account_id).await?;
+            let now = Self::now();
+            let poll = sqlx::query!(
+                r#"
+                    select posts.poll_closes_at as "poll_closes_at!: i64"
+                    from poll_options
+                    join posts on posts.id = poll_options.post_id
+                    where poll_options.id = ? and poll_options.post_id = ?
+                "#,
+                option_id,
+                post_id
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            if (poll.poll_closes_at as f64) <= now {
+                return Err(AppError::PollClosed);
+            }
+            sqlx::query!(
+                "insert into poll_votes (post_id, option_id, account_id, updated_at, created_at) values (?, ?, ?, ?, ?)",
+                post_id,
+                option_id,
+                account_id,
                 now,
                 now
             )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }
+
+        /// Previews are cached for a week, failed fetches too so a dead
+        /// link isn't fetched again every time the post is seen.
+        pub async fn link_preview(
+            &self,
+            post_id: i64,
+            current_account: Option<&Account>,
+        ) -> Result<Option<LinkPreview>> {
+            let _timer = metrics::query_timer("link_preview");
+            let account_id = current_account.map_or(0, |account| account.id);
+            let now = Self::now();
+            let post = sqlx::query!(
+                "select body from posts where id = ? and (publish_at is null or publish_at <= ? or account_id = ?)",
+                post_id,
+                now,
+                account_id
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            let Some(url) = crate::models::first_url(&post.body) else { return Ok(None) };
+            let fresh_since = now - 7.0 * 24.0 * 60.0 * 60.0;
+            let cached = sqlx::query_as!(
+                LinkPreview,
+                "select url, title, description, image_url from link_previews where url = ? and fetched_at > ?",
+                url,
+                fresh_since
+            )
+            .fetch_optional(&self.pool)
+            .await?;
+            let preview = match cached {
+                Some(preview) => preview,
+                None => {
+                    let preview = match self.previews.fetch(url).await {
+                        Ok(html) => previews::parse(url, &html),
+                        Err(err) => {
+                            tracing::info!(%err, url, "link preview wasn't fetched");
+                            LinkPreview {
+                                url: url.to_string(),
+                                ..Default::default()
+                            }
+                        }
+                    };
+                    sqlx::query!(
+                        "insert into link_previews (url, title, description, image_url, fetched_at) values (?, ?, ?, ?, ?) on conflict (url) do update set title = excluded.title, description = excluded.description, image_url = excluded.image_url, fetched_at = excluded.fetched_at",
+                        preview.url,
+                        preview.title,
+                        preview.description,
+                        preview.image_url,
+                        now
+                    )
+                    .execute(&self.pool)
+                    .await?;
+                    preview
+                }
+            };
+            match preview.title.is_some() || preview.description.is_some() {
+                true => Ok(Some(preview)),
+                false => Ok(None),
+            }
+        }
+
+        pub fn upload_path(&self, filename: &str) -> Option<PathBuf> {
+            match uploads::is_valid_name(filename) {
+                true => Some(self.uploads_dir.join(filename)),
+                false => None,
+            }
+        }
+
+        /// Best effort, a file left behind is only wasted space.
+        pub async fn upload_count_since(&self, account_id: i64, since: f64) -> Result<i64> {
+            let _timer = metrics::query_timer("upload_count_since");
+            let row = sqlx::query!(
+                r#"select count(id) as "count!: i64" from attachments where account_id = ? and created_at > ?"#,
+                account_id,
+                since
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(row.count)
+        }
+
+        /// Deletes uploads older than `age` that aren't on a post, returns
+        /// how many were deleted.
+        pub async fn purge_unattached_uploads(&self, age: Duration) -> Result<usize> {
+            let _timer = metrics::query_timer("purge_unattached_uploads");
+            let before = Self::now() - age.as_secs_f64();
+            let rows = sqlx::query!(
+                "delete from attachments where post_id is null and created_at <= ? returning filename",
+                before
+            )
             .fetch_all(&self.pool)
             .await?;
-            let id = rows
-                .first()
-                .expect("post was not inserted into the db correctly")
-                .id;
-            let post = self.post_by_id(id, Some(current_account)).await?;
-            Ok(post)
+            let filenames: Vec<String> = rows.into_iter().map(|row| row.filename).collect();
+            self.remove_uploads(&filenames).await;
+            Ok(filenames.len())
+        }
+
+        async fn remove_uploads(&self, filenames: &[String]) {
+            for filename in filenames {
+                for name in [filename.clone(), uploads::thumbnail_name(filename)] {
+                    if let Err(err) = tokio::fs::remove_file(self.uploads_dir.join(&name)).await {
+                        tracing::warn!("couldn't remove upload {name}: {err}");
+                    }
+                }
+            }
         }
 
         pub async fn post_by_id(&self, id: i64, current_account: Option<Account>) -> Result<Post> {
-            let current_account_id = current_account.unwrap_or_default().id;
-            let post = sqlx::query_as!(
+            let _timer = metrics::query_timer("post_by_id");
+            let account_id = current_account.unwrap_or_default().id;
+            self.select_posts(account_id, PostFilter::Id(id))
+                .await?
+                .pop()
+                .ok_or(AppError::NotFound)
+        }
+
+        pub async fn posts(&self, current_account: Option<&Account>) -> Result<Vec<Post>> {
+            let _timer = metrics::query_timer("posts");
+            let account_id = match current_account {
+                Some(account) => account.id,
+                None => 0,
+            };
+            self.select_posts(account_id, PostFilter::Feed).await
+        }
+
+        /// Every read of posts for someone goes through here, so what they can
+        /// see is decided in one place: posts by accounts pending deletion,
+        /// muted or blocked accounts or with enough open reports are left out,
+        /// and scheduled posts only show to their author. `account_id` is 0
+        /// when nobody is logged in.
+        async fn select_posts(&self, account_id: i64, filter: PostFilter) -> Result<Vec<Post>> {
+            let now = Self::now();
+            let (id, before, limit) = match filter {
+                PostFilter::Feed => (None, None, 30),
+                PostFilter::Id(id) => (Some(id), None, 1),
+                // one extra row says whether there's another page
+                PostFilter::Saved { before } => (None, Some(before), crate::SAVED_PAGE_SIZE + 1),
+            };
+            let posts = sqlx::query_as!(
                 Post,
                 r#"
                     select
                         posts.*,
                         like_counts.like_count as "like_count?: i64",
                         accounts.name as account_name,
+                        accounts.display_name as account_display_name,
                         likes.account_id as liked_by_current_account,
-                        comment_counts.count as "comment_count!: i64"
+                        bookmarks.account_id as "bookmarked_by_current_account?: i64",
+                        bookmarks.id as "bookmark_id?: i64",
+                        comment_counts.count as "comment_count!: i64",
+                        post_attachments.filenames as "attachments?: String",
+                        case
+                            when poll_votes.option_id is not null or posts.poll_closes_at <= ? then polls.results
+                            else polls.options
+                        end as "poll?: String",
+                        poll_votes.option_id as "poll_vote?: i64",
+                        coalesce(repost_counts.count, 0) as "repost_count!: i64",
+                        coalesce(quote_counts.count, 0) as "quote_count!: i64",
+                        my_reposts.account_id as "reposted_by_current_account?: i64",
+                        reposters.name as "reposted_by?: String",
+                        quoted_posts.body as "quoted_body?: String",
+                        quoted_accounts.name as "quoted_account_name?: String"
                     from posts
                     join accounts on accounts.id = posts.account_id
                     left join likes on likes.post_id = posts.id and likes.account_id = ?
+                    left join bookmarks on bookmarks.post_id = posts.id and bookmarks.account_id = ?
+                    left join (
+                        select attachments.post_id, group_concat(attachments.filename) as filenames
+                        from attachments
+                        group by attachments.post_id
+                    ) post_attachments on post_attachments.post_id = posts.id
+                    left join (
+                        select
+                            poll_options.post_id,
+                            json_group_array(json_object('id', poll_options.id, 'body', poll_options.body)) as options,
+                            json_group_array(json_object('id', poll_options.id, 'body', poll_options.body, 'votes', coalesce(vote_counts.count, 0))) as results
+                        from poll_options
+                        left join (
+                            select poll_votes.option_id, count(poll_votes.id) as count
+                            from poll_votes
+                            group by poll_votes.option_id
+                        ) vote_counts on vote_counts.option_id = poll_options.id
+                        group by poll_options.post_id
+                    ) polls on polls.post_id = posts.id
+                    left join poll_votes on poll_votes.post_id = posts.id and poll_votes.account_id = ?
+                    left join reposts my_reposts on my_reposts.post_id = posts.id and my_reposts.account_id = ?
+                    left join (
+                        -- sqlite takes account_id from the row max() picked, the latest reposter
+                        select reposts.post_id, count(reposts.id) as count, max(reposts.created_at) as reposted_at, reposts.account_id
+                        from reposts
+                        join accounts repost_accounts on repost_accounts.id = reposts.account_id
+                        where repost_accounts.deletion_requested_at is null and reposts.account_id not in (
+                            select muted_id from mutes where muter_id = ?
+                            union
+                            select blocked_id from blocks where blocker_id = ?
+                        )
+                        group by reposts.post_id
+                    ) repost_counts on repost_counts.post_id = posts.id
+                    left join accounts reposters on reposters.id = repost_counts.account_id
+                    left join (
+                        select quotes.quoted_post_id, count(quotes.id) as count
+                        from posts quotes
+                        where quotes.quoted_post_id is not null
+                        group by quotes.quoted_post_id
+                    ) quote_counts on quote_counts.quoted_post_id = posts.id
+                    left join posts quoted_posts on quoted_posts.id = posts.quoted_post_id
+                        and quoted_posts.id not in (
+                            select target_id from reports
+                            where target_kind = 'post' and status = 'open'
+                            group by target_id
+                            having count(id) >= ?
+                        ) and quoted_posts.account_id not in (
+                            select muted_id from mutes where muter_id = ?
+                            union
+                            select blocked_id from blocks where blocker_id = ?
+                            union
+                            select id from accounts where deletion_requested_at is not null
+                        )
+                    left join accounts quoted_accounts on quoted_accounts.id = quoted_posts.account_id
                     left join (
                         select likes.post_id, count(likes.id) as like_count
                         from likes
@@ -455,56 +1913,203 @@ mod backend {
                     left join (
                         select comments.post_id, count(comments.id) as count
                         from comments
+                        join accounts comment_accounts on comment_accounts.id = comments.account_id
+                        where comment_accounts.deletion_requested_at is null and comments.id not in (
+                            select target_id from reports
+                            where target_kind = 'comment' and status = 'open'
+                            group by target_id
+                            having count(id) >= ?
+                        ) and comments.account_id not in (
+                            select muted_id from mutes where muter_id = ?
+                            union
+                            select blocked_id from blocks where blocker_id = ?
+                        )
                         group by comments.post_id
                     ) comment_counts on comment_counts.post_id = posts.id
-                    where posts.id = ?
+                    where accounts.deletion_requested_at is null and posts.id not in (
+                        select target_id from reports
+                        where target_kind = 'post' and status = 'open'
+                        group by target_id
+                        having count(id) >= ?
+                    ) and posts.account_id not in (
+                        select muted_id from mutes where muter_id = ?
+                        union
+                        select blocked_id from blocks where blocker_id = ?
+                    ) and (
+                        posts.publish_at is null or posts.publish_at <= ? or posts.account_id = ?
+                    )
+                    and (? is null or posts.id = ?)
+                    and (? is null or bookmarks.id < ?)
+                    order by case
+                        when ? is null then max(
+                            coalesce(posts.publish_at, posts.created_at),
+                            coalesce(repost_counts.reposted_at, 0)
+                        )
+                        else bookmarks.id
+                    end desc
+                    limit ?
                 "#,
-                current_account_id,
-                id
+                now,
+                account_id,
+                account_id,
+                account_id,
+                account_id,
+                account_id,
+                account_id,
+                self.report_threshold,
+                account_id,
+                account_id,
+                self.report_threshold,
+                account_id,
+                account_id,
+                self.report_threshold,
+                account_id,
+                account_id,
+                now,
+                account_id,
+                id,
+                id,
+                before,
+                before,
+                before,
+                limit
             )
-            .fetch_one(&self.pool)
+            .fetch_all(&self.pool)
             .await?;
-            Ok(post)
+            Ok(posts)
         }
 
-        pub async fn posts(&self, current_account: Option<&Account>) -> Result<Vec<Post>> {
-            let account_id = match current_account {
-                Some(account) => account.id,
-                None => 0,
-            };
+        /// The author's posts that haven't been published yet, soonest first.
+        pub async fn scheduled_posts(&self, current_account: &Account) -> Result<Vec<Post>> {
+            let _timer = metrics::query_timer("scheduled_posts");
+            let now = Self::now();
             let posts = sqlx::query_as!(
                 Post,
                 r#"
                     select
                         posts.*,
-                        like_counts.like_count as "like_count?: i64",
                         accounts.name as account_name,
-                        likes.account_id as liked_by_current_account,
-                        comment_counts.count as "comment_count!: i64"
+                        accounts.display_name as account_display_name,
+                        null as "like_count?: i64",
+                        null as "liked_by_current_account?: i64",
+                        null as "bookmarked_by_current_account?: i64",
+                        null as "bookmark_id?: i64",
+                        0 as "comment_count!: i64",
+                        post_attachments.filenames as "attachments?: String",
+                        null as "poll?: String",
+                        null as "poll_vote?: i64",
+                        0 as "repost_count!: i64",
+                        0 as "quote_count!: i64",
+                        null as "reposted_by_current_account?: i64",
+                        null as "reposted_by?: String",
+                        null as "quoted_body?: String",
+                        null as "quoted_account_name?: String"
                     from posts
                     join accounts on accounts.id = posts.account_id
-                    left join likes on likes.post_id = posts.id and likes.account_id = ?
                     left join (
-                        select likes.post_id, count(likes.id) as like_count
-                        from likes
-                        group by likes.post_id
-                    ) like_counts on like_counts.post_id = posts.id
-                    left join (
-                        select comments.post_id, count(comments.post_id) as count
-                        from comments
-                        group by comments.post_id
-                    ) comment_counts on comment_counts.post_id = posts.id
-                    order by posts.created_at desc
-                    limit 30
+                        select attachments.post_id, group_concat(attachments.filename) as filenames
+                        from attachments
+                        group by attachments.post_id
+                    ) post_attachments on post_attachments.post_id = posts.id
+                    where posts.account_id = ? and posts.publish_at > ?
+                    order by posts.publish_at
                 "#,
-                account_id
+                current_account.id,
+                now
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(posts)
+        }
+
+        /// Leaves `publish_at` alone when it's none.
+        pub async fn update_scheduled_post(
+            &self,
+            current_account: Account,
+            id: i64,
+            body: String,
+            publish_at: Option<i64>,
+        ) -> Result<Post> {
+            let _timer = metrics::query_timer("update_scheduled_post");
+            let now = Self::now();
+            // a poll stays open for as long as it was going to, counted
+            // from the new publish time
+            sqlx::query!(
+                "update posts set body = ?, publish_at = coalesce(?, publish_at), poll_closes_at = poll_closes_at + coalesce(?, publish_at) - publish_at, updated_at = ? where id = ? and account_id = ? and publish_at > ? returning id",
+                body,
+                publish_at,
+                publish_at,
+                now,
+                id,
+                current_account.id,
+                now
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            self.post_by_id(id, Some(current_account)).await
+        }
+
+        pub async fn cancel_scheduled_post(&self, account_id: i64, id: i64) -> Result<()> {
+            let _timer = metrics::query_timer("cancel_scheduled_post");
+            let now = Self::now();
+            let mut tx = self.pool.begin().await?;
+            let filenames = Self::delete_attachments_of_post(&mut tx, id).await?;
+            Self::delete_poll_of_post(&mut tx, id).await?;
+            Self::delete_reposts_of_post(&mut tx, id).await?;
+            Self::delete_bookmarks_of_post(&mut tx, id).await?;
+            sqlx::query!(
+                "delete from posts where id = ? and account_id = ? and publish_at > ? returning id",
+                id,
+                account_id,
+                now
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            self.remove_uploads(&filenames).await;
+            Ok(())
+        }
+
+        /// Marks due scheduled posts as published, moving `created_at` to
+        /// the time they went out so they sort with everything else.
+        pub async fn publish_due_posts(&self) -> Result<Vec<PublishedPost>> {
+            let _timer = metrics::query_timer("publish_due_posts");
+            let now = Self::now();
+            let posts = sqlx::query_as!(
+                PublishedPost,
+                r#"update posts set created_at = publish_at, publish_at = null where publish_at <= ? returning id, account_id, created_at"#,
+                now
             )
             .fetch_all(&self.pool)
             .await?;
             Ok(posts)
         }
 
+        /// `NotFound` unless `select_posts` would show the post to the
+        /// account. An `account_id` of 0 stands for nobody being logged in.
+        pub async fn ensure_post_visible(&self, post_id: i64, account_id: i64) -> Result<()> {
+            let _timer = metrics::query_timer("ensure_post_visible");
+            self.select_posts(account_id, PostFilter::Id(post_id))
+                .await?
+                .pop()
+                .map(|_| ())
+                .ok_or(AppError::NotFound)
+        }
+
+        /// Likes, comments, votes, reposts and quotes all go through here:
+        /// the post has to be visible and its author can't have blocked the
+        /// account, which gets `Forbidden`.
+        pub async fn ensure_can_interact(&self, post_id: i64, account_id: i64) -> Result<()> {
+            self.ensure_post_visible(post_id, account_id).await?;
+            match self.blocked_by_post_author(post_id, account_id).await? {
+                true => Err(AppError::Forbidden),
+                false => Ok(()),
+            }
+        }
+
         pub async fn insert_like(&self, account_id: i64, post_id: i64) -> Result<Like> {
+            let _timer = metrics::query_timer("insert_like");
+            self.ensure_can_interact(post_id, account_id).await?;
             let now = Self::now();
             let like = sqlx::query_as!(Like,
                 "insert into likes (account_id, post_id, created_at, updated_at) values (?, ?, ?, ?) returning *", account_id, post_id, now, now)
@@ -514,6 +2119,7 @@ mod backend {
         }
 
         pub async fn delete_like(&self, account_id: i64, post_id: i64) -> Result<bool> {
+            let _timer = metrics::query_timer("delete_like");
             sqlx::query_as!(
                 Like,
                 "delete from likes where post_id = ? and account_id = ?",
@@ -531,17 +2137,16 @@ mod backend {
             account_id: i64,
             body: String,
         ) -> Result<Comment> {
+            let _timer = metrics::query_timer("insert_comment");
+            self.ensure_can_interact(post_id, account_id).await?;
             let now = Self::now();
-            let rows = sqlx::query_as!(Comment, r#"insert into comments (account_id, post_id, body, created_at, updated_at) values (?, ?, ?, ?, ?) returning *, '' as account_name"#, account_id, post_id, body, now, now).fetch_all(&self.pool).await?;
-            let id = rows
-                .first()
-                .expect("Failure inserting comment into the database")
-                .id;
-            let comment = self.comment_by_id(id).await?;
+            let inserted = sqlx::query_as!(Comment, r#"insert into comments (account_id, post_id, body, created_at, updated_at) values (?, ?, ?, ?, ?) returning *, '' as account_name"#, account_id, post_id, body, now, now).fetch_one(&self.pool).await?;
+            let comment = self.comment_by_id(inserted.id).await?;
             Ok(comment)
         }
 
         pub async fn comment_by_id(&self, id: i64) -> Result<Comment> {
+            let _timer = metrics::query_timer("comment_by_id");
             let comment = sqlx::query_as!(
                 Comment,
                 r#"
@@ -549,8 +2154,8 @@ mod backend {
                         comments.*,
                         accounts.name as "account_name!: String"
                     from comments
-                    left outer join accounts on accounts.id = comments.account_id
-                    where comments.id = ?
+                    join accounts on accounts.id = comments.account_id
+                    where comments.id = ? and accounts.deletion_requested_at is null
                     limit 1
                 "#,
                 id
@@ -560,387 +2165,4591 @@ mod backend {
             Ok(comment)
         }
 
-        pub async fn comments_by_post_id(&self, post_id: i64) -> Result<Vec<Comment>> {
-            let comments = sqlx::query_as!(
-                Comment,
+        pub async fn comments_by_post_id(
+            &self,
+            post_id: i64,
+            current_account: Option<&Account>,
+        ) -> Result<Vec<Comment>> {
+            let _timer = metrics::query_timer("comments_by_post_id");
+            let account_id = match current_account {
+                Some(account) => account.id,
+                None => 0,
+            };
+            self.ensure_post_visible(post_id, account_id).await?;
+            let comments = sqlx::query_as!(
+                Comment,
                 r#"
                     select
                         comments.*,
                         accounts.name as "account_name!: String"
                     from comments
                     left outer join accounts on accounts.id = comments.account_id
-                    where comments.post_id = ?
+                    where comments.post_id = ? and accounts.deletion_requested_at is null and comments.id not in (
+                        select target_id from reports
+                        where target_kind = 'comment' and status = 'open'
+                        group by target_id
+                        having count(id) >= ?
+                    ) and comments.account_id not in (
+                        select muted_id from mutes where muter_id = ?
+                        union
+                        select blocked_id from blocks where blocker_id = ?
+                    )
                     order by comments.created_at
                     limit 30
                 "#,
-                post_id
+                post_id,
+                self.report_threshold,
+                account_id,
+                account_id
             )
             .fetch_all(&self.pool)
             .await?;
             Ok(comments)
         }
 
-        pub async fn search(&self, query: String) -> Result<Vec<SearchResult>> {
-            let query = format!("{}%", query);
-            let results = sqlx::query_as!(
-                SearchResult,
-                r#"
-                    select accounts.name
-                    from accounts
-                    where accounts.name like ?
-                    order by accounts.name
-                    limit 30
-                "#,
-                query
+        /// Creates a draft when `id` is none, otherwise updates the
+        /// account's draft with that id.
+        pub async fn save_draft(
+            &self,
+            account_id: i64,
+            id: Option<i64>,
+            body: String,
+        ) -> Result<Draft> {
+            let _timer = metrics::query_timer("save_draft");
+            let now = Self::now();
+            let draft = match id {
+                Some(id) => sqlx::query_as!(
+                    Draft,
+                    "update drafts set body = ?, updated_at = ? where id = ? and account_id = ? returning *",
+                    body,
+                    now,
+                    id,
+                    account_id
+                )
+                .fetch_one(&self.pool)
+                .await?,
+                None => sqlx::query_as!(
+                    Draft,
+                    "insert into drafts (account_id, body, updated_at, created_at) values (?, ?, ?, ?) returning *",
+                    account_id,
+                    body,
+                    now,
+                    now
+                )
+                .fetch_one(&self.pool)
+                .await?,
+            };
+            Ok(draft)
+        }
+
+        pub async fn drafts(&self, account_id: i64) -> Result<Vec<Draft>> {
+            let _timer = metrics::query_timer("drafts");
+            let drafts = sqlx::query_as!(
+                Draft,
+                "select * from drafts where account_id = ? order by updated_at desc limit 30",
+                account_id
             )
             .fetch_all(&self.pool)
             .await?;
-            Ok(results)
+            Ok(drafts)
         }
-    }
-
-    #[derive(Debug, Default)]
-    pub struct Env {
-        pub database_url: String,
-    }
 
-    impl Env {
-        pub fn new() -> Self {
-            Self::parse(Self::read())
+        pub async fn delete_draft(&self, account_id: i64, id: i64) -> Result<Draft> {
+            let _timer = metrics::query_timer("delete_draft");
+            let draft = sqlx::query_as!(
+                Draft,
+                "delete from drafts where id = ? and account_id = ? returning *",
+                id,
+                account_id
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(draft)
         }
 
-        pub fn read() -> String {
-            std::fs::read_to_string(".env").unwrap_or_default()
+        pub async fn post_count_since(&self, account_id: i64, since: f64) -> Result<i64> {
+            let _timer = metrics::query_timer("post_count_since");
+            let row = sqlx::query!(
+                r#"select count(id) as "count!: i64" from posts where account_id = ? and created_at > ?"#,
+                account_id,
+                since
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(row.count)
         }
 
-        pub fn parse(file: String) -> Self {
-            let data = file
-                .lines()
-                .flat_map(|line| line.split("="))
-                .collect::<Vec<_>>()
-                .chunks_exact(2)
-                .map(|x| (x[0], x[1]))
-                .collect::<HashMap<_, _>>();
-            Self {
-                database_url: data
-                    .get("DATABASE_URL")
-                    .expect("DATABASE_URL is missing")
-                    .to_string(),
-            }
+        pub async fn comment_count_since(&self, account_id: i64, since: f64) -> Result<i64> {
+            let _timer = metrics::query_timer("comment_count_since");
+            let row = sqlx::query!(
+                r#"select count(id) as "count!: i64" from comments where account_id = ? and created_at > ?"#,
+                account_id,
+                since
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(row.count)
         }
-    }
-}
 
-pub mod models {
-    use serde::{Deserialize, Serialize};
+        pub async fn account_by_name(&self, name: &str) -> Result<Account> {
+            let _timer = metrics::query_timer("account_by_name");
+            let account = sqlx::query_as!(
+                Account,
+                r#"select id, name, display_name, is_admin as "is_admin: bool", updated_at, created_at from accounts where name = ? limit 1"#,
+                name
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(account)
+        }
 
-    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
-    pub struct Account {
-        pub id: i64,
-        pub name: String,
-        pub login_code: String,
-        pub updated_at: i64,
-        pub created_at: i64,
-    }
+        /// Finds an account by its handle, or by one it gave up recently.
+        pub async fn account_by_handle(&self, name: &str) -> Result<Account> {
+            let _timer = metrics::query_timer("account_by_handle");
+            let now = Self::now();
+            let account = sqlx::query_as!(
+                Account,
+                r#"
+                    select id, name, display_name, is_admin as "is_admin: bool", updated_at, created_at
+                    from accounts
+                    where (
+                        name = ? or id in (select account_id from handle_redirects where name = ? and expires_at > ?)
+                    ) and suspended_at is null and deletion_requested_at is null
+                    limit 1
+                "#,
+                name,
+                name,
+                now
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(account)
+        }
 
-    #[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
-    pub struct Session {
-        pub id: i64,
-        pub identifier: String,
-        pub account_id: i64,
-        pub updated_at: i64,
-        pub created_at: i64,
-    }
+        pub async fn set_display_name(
+            &self,
+            account_id: i64,
+            display_name: Option<String>,
+        ) -> Result<Account> {
+            let _timer = metrics::query_timer("set_display_name");
+            let now = Self::now();
+            let account = sqlx::query_as!(
+                Account,
+                r#"update accounts set display_name = ?, updated_at = ? where id = ? returning id, name, display_name, is_admin as "is_admin: bool", updated_at, created_at"#,
+                display_name,
+                now,
+                account_id
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(account)
+        }
 
-    #[derive(Clone, Default, Serialize, Deserialize, PartialEq, Debug)]
-    pub struct Post {
-        pub id: i64,
-        pub body: String,
-        pub account_id: i64,
-        pub account_name: String,
-        pub like_count: Option<i64>,
-        pub liked_by_current_account: Option<i64>,
-        pub updated_at: i64,
-        pub created_at: i64,
-        pub comment_count: i64,
-    }
+        pub async fn profile_fields(&self, account_id: i64) -> Result<ProfileFields> {
+            let _timer = metrics::query_timer("profile_fields");
+            let fields = sqlx::query_as!(
+                ProfileFields,
+                "select bio, website, pronouns from accounts where id = ? and suspended_at is null and deletion_requested_at is null",
+                account_id
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(fields)
+        }
 
-    pub trait HasAccount {
-        fn account(&self) -> Account;
-    }
+        pub async fn set_profile_fields(
+            &self,
+            account_id: i64,
+            fields: ProfileFields,
+        ) -> Result<ProfileFields> {
+            let _timer = metrics::query_timer("set_profile_fields");
+            let now = Self::now();
+            let fields = sqlx::query_as!(
+                ProfileFields,
+                "update accounts set bio = ?, website = ?, pronouns = ?, updated_at = ? where id = ? returning bio, website, pronouns",
+                fields.bio,
+                fields.website,
+                fields.pronouns,
+                now,
+                account_id
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(fields)
+        }
 
-    impl HasAccount for Post {
-        fn account(&self) -> Account {
-            Account {
-                name: self.account_name.clone(),
-                id: self.account_id,
-                ..Default::default()
+        /// Moves an account to a new handle. The old one keeps pointing at
+        /// the account for `HANDLE_REDIRECT_SECS` so shared links still work,
+        /// and nobody else can claim it in the meantime.
+        pub async fn change_name(&self, account_id: i64, name: String) -> Result<Account> {
+            let _timer = metrics::query_timer("change_name");
+            let now = Self::now();
+            let skeleton = crate::name_skeleton(&name);
+            let mut tx = self.pool.begin().await?;
+            let current = sqlx::query!(
+                "select name, name_changed_at from accounts where id = ?",
+                account_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if let Some(changed_at) = current.name_changed_at {
+                if (changed_at + crate::NAME_CHANGE_COOLDOWN_SECS) as f64 > now {
+                    return Err(AppError::NameChangeTooSoon);
+                }
             }
+            let taken = sqlx::query!(
+                r#"
+                    select count(*) as "count!: i64" from (
+                        select id from accounts where name_skeleton = ? and id != ?
+                        union all
+                        select account_id from handle_redirects where name_skeleton = ? and account_id != ? and expires_at > ?
+                    )
+                "#,
+                skeleton,
+                account_id,
+                skeleton,
+                account_id,
+                now
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if taken.count > 0 {
+                return Err(AppError::DatabaseUniqueIndex);
+            }
+            // taking back an old handle, or one whose redirect ran out
+            sqlx::query!(
+                "delete from handle_redirects where name_skeleton = ?",
+                skeleton
+            )
+            .execute(&mut *tx)
+            .await?;
+            let old_skeleton = crate::name_skeleton(&current.name);
+            let expires_at = now + crate::HANDLE_REDIRECT_SECS as f64;
+            sqlx::query!(
+                "insert into handle_redirects (name, name_skeleton, account_id, expires_at, created_at) values (?, ?, ?, ?, ?) on conflict (name) do update set name_skeleton = excluded.name_skeleton, account_id = excluded.account_id, expires_at = excluded.expires_at, created_at = excluded.created_at",
+                current.name,
+                old_skeleton,
+                account_id,
+                expires_at,
+                now
+            )
+            .execute(&mut *tx)
+            .await?;
+            let account = sqlx::query_as!(
+                Account,
+                r#"update accounts set name = ?, name_skeleton = ?, name_changed_at = ?, updated_at = ? where id = ? returning id, name, display_name, is_admin as "is_admin: bool", updated_at, created_at"#,
+                name,
+                skeleton,
+                now,
+                now,
+                account_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            Ok(account)
         }
-    }
 
-    impl Account {
-        pub fn initial(&self) -> String {
-            self.name.chars().next().unwrap().to_string()
+        /// Writes everything an account has made as one json object, a row
+        /// at a time so big accounts don't end up in memory all at once.
+        /// Session identifiers are left out, they're as good as a password.
+        pub async fn export_account(
+            &self,
+            account_id: i64,
+            writer: &mut (impl AsyncWrite + Unpin),
+        ) -> Result<()> {
+            let _timer = metrics::query_timer("export_account");
+            let account = sqlx::query_as!(
+                ExportedAccount,
+                "select id, name, display_name, bio, website, pronouns, updated_at, created_at from accounts where id = ?",
+                account_id
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            writer.write_all(br#"{"account":"#).await?;
+            writer.write_all(&serde_json::to_vec(&account)?).await?;
+            let posts = sqlx::query_as!(
+                ExportedPost,
+                "select id, body, quoted_post_id, updated_at, created_at from posts where account_id = ? order by created_at",
+                account_id
+            )
+            .fetch(&self.pool);
+            Self::write_json_array(writer, "posts", posts).await?;
+            let comments = sqlx::query_as!(
+                ExportedComment,
+                "select id, post_id, body, updated_at, created_at from comments where account_id = ? order by created_at",
+                account_id
+            )
+            .fetch(&self.pool);
+            Self::write_json_array(writer, "comments", comments).await?;
+            let likes = sqlx::query_as!(
+                Like,
+                "select * from likes where account_id = ? order by created_at",
+                account_id
+            )
+            .fetch(&self.pool);
+            Self::write_json_array(writer, "likes", likes).await?;
+            let reposts = sqlx::query_as!(
+                Repost,
+                "select * from reposts where account_id = ? order by created_at",
+                account_id
+            )
+            .fetch(&self.pool);
+            Self::write_json_array(writer, "reposts", reposts).await?;
+            let bookmarks = sqlx::query_as!(
+                Bookmark,
+                "select * from bookmarks where account_id = ? order by created_at",
+                account_id
+            )
+            .fetch(&self.pool);
+            Self::write_json_array(writer, "bookmarks", bookmarks).await?;
+            let poll_votes = sqlx::query_as!(
+                PollVote,
+                "select * from poll_votes where account_id = ? order by created_at",
+                account_id
+            )
+            .fetch(&self.pool);
+            Self::write_json_array(writer, "poll_votes", poll_votes).await?;
+            let drafts = sqlx::query_as!(
+                Draft,
+                "select * from drafts where account_id = ? order by created_at",
+                account_id
+            )
+            .fetch(&self.pool);
+            Self::write_json_array(writer, "drafts", drafts).await?;
+            let attachments = sqlx::query_as!(
+                Attachment,
+                "select * from attachments where account_id = ? order by created_at",
+                account_id
+            )
+            .fetch(&self.pool);
+            Self::write_json_array(writer, "attachments", attachments).await?;
+            let sessions = sqlx::query_as!(
+                ExportedSession,
+                "select id, updated_at, created_at from sessions where account_id = ? order by created_at",
+                account_id
+            )
+            .fetch(&self.pool);
+            Self::write_json_array(writer, "sessions", sessions).await?;
+            writer.write_all(b"}\n").await?;
+            writer.flush().await?;
+            Ok(())
         }
-    }
-
-    #[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
-    pub struct InsertPost {
-        pub id: i64,
-    }
 
-    #[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
-    pub struct Like {
-        pub id: i64,
-        pub account_id: i64,
-        pub post_id: i64,
-        pub updated_at: i64,
-        pub created_at: i64,
-    }
+        async fn write_json_array<T: Serialize>(
+            writer: &mut (impl AsyncWrite + Unpin),
+            key: &str,
+            mut rows: BoxStream<'_, std::result::Result<T, sqlx::Error>>,
+        ) -> Result<()> {
+            writer
+                .write_all(format!(r#","{key}":["#).as_bytes())
+                .await?;
+            let mut first = true;
+            while let Some(row) = rows.try_next().await? {
+                if !first {
+                    writer.write_all(b",").await?;
+                }
+                first = false;
+                writer.write_all(&serde_json::to_vec(&row)?).await?;
+            }
+            writer.write_all(b"]").await?;
+            Ok(())
+        }
 
-    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
-    pub struct Comment {
-        pub id: i64,
-        pub account_id: i64,
-        pub account_name: String,
-        pub post_id: i64,
-        pub body: String,
-        pub updated_at: i64,
-        pub created_at: i64,
-    }
+        pub async fn set_blocked(
+            &self,
+            blocker_id: i64,
+            blocked_id: i64,
+            blocked: bool,
+        ) -> Result<()> {
+            let _timer = metrics::query_timer("set_blocked");
+            let now = Self::now();
+            match blocked {
+                true => sqlx::query!(
+                    "insert into blocks (blocker_id, blocked_id, updated_at, created_at) values (?, ?, ?, ?) on conflict do nothing",
+                    blocker_id,
+                    blocked_id,
+                    now,
+                    now
+                )
+                .execute(&self.pool)
+                .await?,
+                false => sqlx::query!(
+                    "delete from blocks where blocker_id = ? and blocked_id = ?",
+                    blocker_id,
+                    blocked_id
+                )
+                .execute(&self.pool)
+                .await?,
+            };
+            Ok(())
+        }
 
-    impl HasAccount for Comment {
-        fn account(&self) -> Account {
-            Account {
-                name: self.account_name.clone(),
-                id: self.account_id,
-                ..Default::default()
+        pub async fn set_bookmarked(
+            &self,
+            account_id: i64,
+            post_id: i64,
+            bookmarked: bool,
+        ) -> Result<()> {
+            let _timer = metrics::query_timer("set_bookmarked");
+            if bookmarked {
+                self.ensure_post_visible(post_id, account_id).await?;
             }
+            let now = Self::now();
+            match bookmarked {
+                true => sqlx::query!(
+                    "insert into bookmarks (account_id, post_id, updated_at, created_at) values (?, ?, ?, ?) on conflict do nothing",
+                    account_id,
+                    post_id,
+                    now,
+                    now
+                )
+                .execute(&self.pool)
+                .await?,
+                false => sqlx::query!(
+                    "delete from bookmarks where account_id = ? and post_id = ?",
+                    account_id,
+                    post_id
+                )
+                .execute(&self.pool)
+                .await?,
+            };
+            Ok(())
         }
-    }
 
-    #[derive(Clone, Default, Serialize, Deserialize, PartialEq, Debug)]
-    pub struct SearchResult {
-        pub name: String,
-    }
-}
+        /// Newest bookmark first. `before` is the `bookmark_id` of the last
+        /// post on the previous page, so unsaving doesn't shift the next one.
+        pub async fn bookmarked_posts(
+            &self,
+            current_account: &Account,
+            before: Option<i64>,
+        ) -> Result<SavedPage> {
+            let _timer = metrics::query_timer("bookmarked_posts");
+            let before = before.unwrap_or(i64::MAX);
+            let mut posts = self
+                .select_posts(current_account.id, PostFilter::Saved { before })
+                .await?;
+            let has_more = posts.len() as i64 > crate::SAVED_PAGE_SIZE;
+            posts.truncate(crate::SAVED_PAGE_SIZE as usize);
+            Ok(SavedPage { posts, has_more })
+        }
 
-#[Error]
-#[derive(Clone, Serialize, Deserialize)]
-pub enum AppError {
-    NotFound,
-    Utf8,
-    Http,
-    AssetExt,
-    Migrate,
-    DatabaseInsert,
-    DatabaseSelect,
-    Database,
-    Rollback,
-    DatabaseUniqueIndex,
-}
+        /// Scheduled posts can't be reposted until they're published.
+        pub async fn set_reposted(
+            &self,
+            account_id: i64,
+            post_id: i64,
+            reposted: bool,
+        ) -> Result<()> {
+            let _timer = metrics::query_timer("set_reposted");
+            if reposted {
+                self.ensure_can_interact(post_id, account_id).await?;
+            }
+            let now = Self::now();
+            match reposted {
+                true => sqlx::query!(
+                    "insert into reposts (account_id, post_id, updated_at, created_at) select ?, posts.id, ?, ? from posts where posts.id = ? and (posts.publish_at is null or posts.publish_at <= ?) on conflict do nothing",
+                    account_id,
+                    now,
+                    now,
+                    post_id,
+                    now
+                )
+                .execute(&self.pool)
+                .await?,
+                false => sqlx::query!(
+                    "delete from reposts where account_id = ? and post_id = ?",
+                    account_id,
+                    post_id
+                )
+                .execute(&self.pool)
+                .await?,
+            };
+            Ok(())
+        }
+
+        pub async fn set_muted(&self, muter_id: i64, muted_id: i64, muted: bool) -> Result<()> {
+            let _timer = metrics::query_timer("set_muted");
+            let now = Self::now();
+            match muted {
+                true => sqlx::query!(
+                    "insert into mutes (muter_id, muted_id, updated_at, created_at) values (?, ?, ?, ?) on conflict do nothing",
+                    muter_id,
+                    muted_id,
+                    now,
+                    now
+                )
+                .execute(&self.pool)
+                .await?,
+                false => sqlx::query!(
+                    "delete from mutes where muter_id = ? and muted_id = ?",
+                    muter_id,
+                    muted_id
+                )
+                .execute(&self.pool)
+                .await?,
+            };
+            Ok(())
+        }
+
+        pub async fn account_relations(&self, account_id: i64) -> Result<AccountRelations> {
+            let _timer = metrics::query_timer("account_relations");
+            let blocked = sqlx::query_as!(
+                PublicAccount,
+                r#"
+                    select accounts.id, accounts.name, accounts.display_name
+                    from blocks
+                    join accounts on accounts.id = blocks.blocked_id
+                    where blocks.blocker_id = ?
+                    order by accounts.name
+                "#,
+                account_id
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            let muted = sqlx::query_as!(
+                PublicAccount,
+                r#"
+                    select accounts.id, accounts.name, accounts.display_name
+                    from mutes
+                    join accounts on accounts.id = mutes.muted_id
+                    where mutes.muter_id = ?
+                    order by accounts.name
+                "#,
+                account_id
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(AccountRelations { blocked, muted })
+        }
+
+        /// Whether the author of a post has blocked `account_id`.
+        pub async fn blocked_by_post_author(&self, post_id: i64, account_id: i64) -> Result<bool> {
+            let _timer = metrics::query_timer("blocked_by_post_author");
+            let row = sqlx::query!(
+                r#"
+                    select blocks.id
+                    from blocks
+                    join posts on posts.account_id = blocks.blocker_id
+                    where posts.id = ? and blocks.blocked_id = ?
+                "#,
+                post_id,
+                account_id
+            )
+            .fetch_optional(&self.pool)
+            .await?;
+            Ok(row.is_some())
+        }
+
+        pub async fn set_admin(&self, name: &str, is_admin: bool) -> Result<Account> {
+            let _timer = metrics::query_timer("set_admin");
+            let account = sqlx::query_as!(
+                Account,
+                r#"update accounts set is_admin = ? where name = ? returning id, name, display_name, is_admin as "is_admin: bool", updated_at, created_at"#,
+                is_admin,
+                name
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(account)
+        }
+
+        pub async fn recent_accounts(&self) -> Result<Vec<AdminAccount>> {
+            let _timer = metrics::query_timer("recent_accounts");
+            let accounts = sqlx::query_as!(
+                AdminAccount,
+                r#"
+                    select id, name, is_admin as "is_admin: bool", suspended_at, created_at
+                    from accounts
+                    order by created_at desc
+                    limit 50
+                "#
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(accounts)
+        }
+
+        pub async fn recent_comments(&self) -> Result<Vec<Comment>> {
+            let _timer = metrics::query_timer("recent_comments");
+            let comments = sqlx::query_as!(
+                Comment,
+                r#"
+                    select
+                        comments.*,
+                        accounts.name as "account_name!: String"
+                    from comments
+                    left outer join accounts on accounts.id = comments.account_id
+                    order by comments.created_at desc
+                    limit 50
+                "#
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(comments)
+        }
+
+        pub async fn moderation_actions(&self) -> Result<Vec<ModerationAction>> {
+            let _timer = metrics::query_timer("moderation_actions");
+            let actions = sqlx::query_as!(
+                ModerationAction,
+                r#"
+                    select
+                        moderation_actions.id,
+                        accounts.name as moderator_name,
+                        moderation_actions.action,
+                        moderation_actions.target_kind,
+                        moderation_actions.target_id,
+                        moderation_actions.summary,
+                        moderation_actions.created_at
+                    from moderation_actions
+                    left outer join accounts on accounts.id = moderation_actions.moderator_id
+                    order by moderation_actions.created_at desc
+                    limit 100
+                "#
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(actions)
+        }
+
+        async fn log_moderation(
+            tx: &mut Transaction<'_, Sqlite>,
+            moderator_id: i64,
+            action: &str,
+            target_kind: &str,
+            target_id: i64,
+            summary: &str,
+        ) -> Result<()> {
+            let now = Self::now();
+            sqlx::query!(
+                "insert into moderation_actions (moderator_id, action, target_kind, target_id, summary, updated_at, created_at) values (?, ?, ?, ?, ?, ?, ?)",
+                moderator_id,
+                action,
+                target_kind,
+                target_id,
+                summary,
+                now,
+                now
+            )
+            .execute(&mut **tx)
+            .await?;
+            Ok(())
+        }
+
+        pub async fn moderate_delete_post(&self, moderator_id: i64, post_id: i64) -> Result<()> {
+            let _timer = metrics::query_timer("moderate_delete_post");
+            let mut tx = self.pool.begin().await?;
+            let filenames =
+                Self::delete_reported(&mut tx, moderator_id, &ReportTarget::Post(post_id)).await?;
+            tx.commit().await?;
+            self.remove_uploads(&filenames).await;
+            Ok(())
+        }
+
+        pub async fn moderate_delete_comment(
+            &self,
+            moderator_id: i64,
+            comment_id: i64,
+        ) -> Result<()> {
+            let _timer = metrics::query_timer("moderate_delete_comment");
+            let mut tx = self.pool.begin().await?;
+            Self::delete_reported(&mut tx, moderator_id, &ReportTarget::Comment(comment_id))
+                .await?;
+            tx.commit().await?;
+            Ok(())
+        }
+
+        /// Deletes a post or comment for a moderator, closing any open
+        /// reports against it as actioned. Returns the uploads to remove
+        /// once the transaction commits.
+        async fn delete_reported(
+            tx: &mut Transaction<'_, Sqlite>,
+            moderator_id: i64,
+            target: &ReportTarget,
+        ) -> Result<Vec<String>> {
+            let mut filenames = vec![];
+            let body = match target {
+                ReportTarget::Post(post_id) => {
+                    let post = sqlx::query!("select body from posts where id = ?", post_id)
+                        .fetch_one(&mut **tx)
+                        .await?;
+                    filenames = Self::delete_attachments_of_post(tx, *post_id).await?;
+                    Self::delete_poll_of_post(tx, *post_id).await?;
+                    Self::delete_reposts_of_post(tx, *post_id).await?;
+                    Self::delete_bookmarks_of_post(tx, *post_id).await?;
+                    sqlx::query!("delete from likes where post_id = ?", post_id)
+                        .execute(&mut **tx)
+                        .await?;
+                    sqlx::query!("delete from comments where post_id = ?", post_id)
+                        .execute(&mut **tx)
+                        .await?;
+                    sqlx::query!("delete from posts where id = ?", post_id)
+                        .execute(&mut **tx)
+                        .await?;
+                    post.body
+                }
+                ReportTarget::Comment(comment_id) => {
+                    let comment = sqlx::query!(
+                        "delete from comments where id = ? returning body",
+                        comment_id
+                    )
+                    .fetch_one(&mut **tx)
+                    .await?;
+                    comment.body
+                }
+            };
+            Self::close_reports(tx, moderator_id, target, "actioned").await?;
+            Self::log_moderation(
+                tx,
+                moderator_id,
+                "delete",
+                target.kind(),
+                target.id(),
+                &body,
+            )
+            .await?;
+            Ok(filenames)
+        }
+
+        async fn delete_bookmarks_of_post(
+            tx: &mut Transaction<'_, Sqlite>,
+            post_id: i64,
+        ) -> Result<()> {
+            sqlx::query!("delete from bookmarks where post_id = ?", post_id)
+                .execute(&mut **tx)
+                .await?;
+            Ok(())
+        }
+
+        /// Quotes of a deleted post stay up as plain posts.
+        async fn delete_reposts_of_post(
+            tx: &mut Transaction<'_, Sqlite>,
+            post_id: i64,
+        ) -> Result<()> {
+            sqlx::query!("delete from reposts where post_id = ?", post_id)
+                .execute(&mut **tx)
+                .await?;
+            sqlx::query!(
+                "update posts set quoted_post_id = null where quoted_post_id = ?",
+                post_id
+            )
+            .execute(&mut **tx)
+            .await?;
+            Ok(())
+        }
+
+        async fn delete_poll_of_post(tx: &mut Transaction<'_, Sqlite>, post_id: i64) -> Result<()> {
+            sqlx::query!("delete from poll_votes where post_id = ?", post_id)
+                .execute(&mut **tx)
+                .await?;
+            sqlx::query!("delete from poll_options where post_id = ?", post_id)
+                .execute(&mut **tx)
+                .await?;
+            Ok(())
+        }
+
+        async fn delete_attachments_of_post(
+            tx: &mut Transaction<'_, Sqlite>,
+            post_id: i64,
+        ) -> Result<Vec<String>> {
+            let rows = sqlx::query!(
+                "delete from attachments where post_id = ? returning filename",
+                post_id
+            )
+            .fetch_all(&mut **tx)
+            .await?;
+            Ok(rows.into_iter().map(|row| row.filename).collect())
+        }
+
+        async fn close_reports(
+            tx: &mut Transaction<'_, Sqlite>,
+            moderator_id: i64,
+            target: &ReportTarget,
+            status: &str,
+        ) -> Result<u64> {
+            let now = Self::now();
+            let kind = target.kind();
+            let id = target.id();
+            let result = sqlx::query!(
+                "update reports set status = ?, resolved_by = ?, updated_at = ? where target_kind = ? and target_id = ? and status = 'open'",
+                status,
+                moderator_id,
+                now,
+                kind,
+                id
+            )
+            .execute(&mut **tx)
+            .await?;
+            Ok(result.rows_affected())
+        }
+
+        pub async fn insert_report(
+            &self,
+            reporter_id: i64,
+            target: &ReportTarget,
+            reason: ReportReason,
+        ) -> Result<()> {
+            let _timer = metrics::query_timer("insert_report");
+            let exists = match target {
+                ReportTarget::Post(id) => sqlx::query!("select id from posts where id = ?", id)
+                    .fetch_optional(&self.pool)
+                    .await?
+                    .is_some(),
+                ReportTarget::Comment(id) => {
+                    sqlx::query!("select id from comments where id = ?", id)
+                        .fetch_optional(&self.pool)
+                        .await?
+                        .is_some()
+                }
+            };
+            if !exists {
+                return Err(AppError::NotFound);
+            }
+            let now = Self::now();
+            let kind = target.kind();
+            let id = target.id();
+            let reason = reason.as_str();
+            sqlx::query!(
+                "insert into reports (reporter_id, target_kind, target_id, reason, updated_at, created_at) values (?, ?, ?, ?, ?, ?)",
+                reporter_id,
+                kind,
+                id,
+                reason,
+                now,
+                now
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }
+
+        /// Open reports grouped by what they point at, most reported first.
+        pub async fn report_queue(&self) -> Result<Vec<ReportSummary>> {
+            let _timer = metrics::query_timer("report_queue");
+            let reports = sqlx::query_as!(
+                ReportSummary,
+                r#"
+                    select
+                        reports.target_kind,
+                        reports.target_id,
+                        accounts.name as "account_name!: String",
+                        coalesce(posts.body, comments.body) as "body!: String",
+                        group_concat(distinct reports.reason) as "reasons!: String",
+                        count(reports.id) as "report_count!: i64",
+                        min(reports.created_at) as "created_at!: i64"
+                    from reports
+                    left join posts on reports.target_kind = 'post' and posts.id = reports.target_id
+                    left join comments on reports.target_kind = 'comment' and comments.id = reports.target_id
+                    join accounts on accounts.id = coalesce(posts.account_id, comments.account_id)
+                    where reports.status = 'open'
+                    group by reports.target_kind, reports.target_id
+                    order by count(reports.id) desc, min(reports.created_at)
+                    limit 50
+                "#
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(reports)
+        }
+
+        pub async fn resolve_reports(
+            &self,
+            moderator_id: i64,
+            target: &ReportTarget,
+            resolution: ReportResolution,
+        ) -> Result<()> {
+            let _timer = metrics::query_timer("resolve_reports");
+            let mut tx = self.pool.begin().await?;
+            let mut filenames = vec![];
+            match resolution {
+                ReportResolution::Action => {
+                    filenames = Self::delete_reported(&mut tx, moderator_id, target).await?;
+                }
+                ReportResolution::Dismiss => {
+                    let count =
+                        Self::close_reports(&mut tx, moderator_id, target, "dismissed").await?;
+                    if count == 0 {
+                        return Err(AppError::NotFound);
+                    }
+                    let summary = format!("{count} reports");
+                    Self::log_moderation(
+                        &mut tx,
+                        moderator_id,
+                        "dismiss",
+                        target.kind(),
+                        target.id(),
+                        &summary,
+                    )
+                    .await?;
+                }
+            }
+            tx.commit().await?;
+            self.remove_uploads(&filenames).await;
+            Ok(())
+        }
+
+        /// Suspended accounts can't log in and their sessions are gone,
+        /// unsuspending lets them back in with the same login code.
+        pub async fn moderate_suspend_account(
+            &self,
+            moderator_id: i64,
+            account_id: i64,
+            suspend: bool,
+        ) -> Result<()> {
+            let _timer = metrics::query_timer("moderate_suspend_account");
+            let now = Self::now();
+            let suspended_at = suspend.then_some(now);
+            let mut tx = self.pool.begin().await?;
+            let account = sqlx::query!(
+                "update accounts set suspended_at = ?, updated_at = ? where id = ? and is_admin = 0 returning name",
+                suspended_at,
+                now,
+                account_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            sqlx::query!("delete from sessions where account_id = ?", account_id)
+                .execute(&mut *tx)
+                .await?;
+            let action = match suspend {
+                true => "suspend",
+                false => "unsuspend",
+            };
+            Self::log_moderation(
+                &mut tx,
+                moderator_id,
+                action,
+                "account",
+                account_id,
+                &account.name,
+            )
+            .await?;
+            tx.commit().await?;
+            Ok(())
+        }
+
+        pub async fn search(
+            &self,
+            query: String,
+            current_account: Option<&Account>,
+        ) -> Result<Vec<SearchResult>> {
+            let _timer = metrics::query_timer("search");
+            let query = format!("{}%", query);
+            let account_id = match current_account {
+                Some(account) => account.id,
+                None => 0,
+            };
+            let results = sqlx::query_as!(
+                SearchResult,
+                r#"
+                    select accounts.id, accounts.name
+                    from accounts
+                    where accounts.name like ? and accounts.suspended_at is null and accounts.deletion_requested_at is null and accounts.id not in (
+                        select muted_id from mutes where muter_id = ?
+                        union
+                        select blocked_id from blocks where blocker_id = ?
+                    )
+                    order by accounts.name
+                    limit 30
+                "#,
+                query,
+                account_id,
+                account_id
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(results)
+        }
+    }
+
+    /// Link previews come from the Open Graph tags of the first url in a
+    /// post. Fetching goes through `PreviewFetcher` so tests don't need
+    /// the network.
+    pub mod previews {
+        use crate::{models::LinkPreview, AppError};
+        use futures_util::future::BoxFuture;
+        use reqwest::{redirect::Policy, Url};
+        use std::{
+            fmt::Debug,
+            net::{IpAddr, SocketAddr},
+            time::Duration,
+        };
+
+        pub const MAX_BYTES: usize = 256 * 1024;
+        const MAX_REDIRECTS: usize = 3;
+        const TIMEOUT: Duration = Duration::from_secs(5);
+
+        pub trait PreviewFetcher: Debug + Send + Sync {
+            /// The html at `url`, or an error if it can't or shouldn't be fetched.
+            fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String, AppError>>;
+        }
+
+        #[derive(Debug)]
+        pub struct HttpFetcher {
+            /// Which addresses can be fetched, tests let loopback through.
+            allowed: fn(IpAddr) -> bool,
+        }
+
+        impl HttpFetcher {
+            pub fn allowing(allowed: fn(IpAddr) -> bool) -> Self {
+                Self { allowed }
+            }
+        }
+
+        impl Default for HttpFetcher {
+            fn default() -> Self {
+                Self::allowing(is_public)
+            }
+        }
+
+        impl PreviewFetcher for HttpFetcher {
+            fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String, AppError>> {
+                Box::pin(async move {
+                    tokio::time::timeout(TIMEOUT, fetch_html(url, self.allowed))
+                        .await
+                        .map_err(|_| AppError::Preview("timed out".to_string()))?
+                })
+            }
+        }
+
+        /// Follows redirects by hand so every hop gets its address checked,
+        /// and connects to the address that was checked rather than looking
+        /// the host up again.
+        async fn fetch_html(url: &str, allowed: fn(IpAddr) -> bool) -> Result<String, AppError> {
+            let mut url = Url::parse(url).map_err(|err| AppError::Preview(err.to_string()))?;
+            for _ in 0..=MAX_REDIRECTS {
+                let addr = allowed_addr(&url, allowed).await?;
+                let host = url.host_str().unwrap_or_default().to_string();
+                let client = reqwest::Client::builder()
+                    .redirect(Policy::none())
+                    .timeout(TIMEOUT)
+                    .user_agent("yallpost link preview")
+                    .resolve(&host, addr)
+                    .build()
+                    .map_err(preview_error)?;
+                let mut response = client
+                    .get(url.clone())
+                    .send()
+                    .await
+                    .map_err(preview_error)?;
+                if response.status().is_redirection() {
+                    let location = response
+                        .headers()
+                        .get(reqwest::header::LOCATION)
+                        .and_then(|value| value.to_str().ok())
+                        .ok_or_else(|| {
+                            AppError::Preview("redirect without a location".to_string())
+                        })?;
+                    url = url
+                        .join(location)
+                        .map_err(|err| AppError::Preview(err.to_string()))?;
+                    continue;
+                }
+                let is_html = response
+                    .headers()
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .map_or(false, |value| value.starts_with("text/html"));
+                if !response.status().is_success() || !is_html {
+                    return Err(AppError::Preview(format!("{} isn't an html page", url)));
+                }
+                // the head is all that's needed, stop reading once there's enough
+                let mut body = vec![];
+                while let Some(chunk) = response.chunk().await.map_err(preview_error)? {
+                    body.extend_from_slice(&chunk);
+                    if body.len() >= MAX_BYTES {
+                        body.truncate(MAX_BYTES);
+                        break;
+                    }
+                }
+                return Ok(String::from_utf8_lossy(&body).into_owned());
+            }
+            Err(AppError::Preview("too many redirects".to_string()))
+        }
+
+        fn preview_error(err: reqwest::Error) -> AppError {
+            AppError::Preview(err.to_string())
+        }
+
+        async fn allowed_addr(
+            url: &Url,
+            allowed: fn(IpAddr) -> bool,
+        ) -> Result<SocketAddr, AppError> {
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(AppError::Preview(format!("{} isn't http", url)));
+            }
+            let host = url
+                .host_str()
+                .ok_or_else(|| AppError::Preview(format!("{} has no host", url)))?;
+            let port = url.port_or_known_default().unwrap_or(80);
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|err| AppError::Preview(err.to_string()))?
+                .collect();
+            match addrs.iter().all(|addr| allowed(addr.ip())) {
+                true => addrs
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| AppError::Preview(format!("{host} didn't resolve"))),
+                false => Err(AppError::Preview(format!("{host} is a private address"))),
+            }
+        }
+
+        pub fn is_public(ip: IpAddr) -> bool {
+            match ip {
+                IpAddr::V4(ip) => {
+                    let [a, b, ..] = ip.octets();
+                    !(ip.is_private()
+                        || ip.is_loopback()
+                        || ip.is_link_local()
+                        || ip.is_unspecified()
+                        || ip.is_broadcast()
+                        || ip.is_documentation()
+                        || ip.is_multicast()
+                        || a == 0
+                        || a >= 240
+                        || (a == 100 && (64..128).contains(&b))
+                        || (a == 198 && (18..20).contains(&b)))
+                }
+                IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+                    Some(ip) => is_public(IpAddr::V4(ip)),
+                    None => {
+                        let segments = ip.segments();
+                        let first = segments[0];
+                        // nat64, 6to4 and ipv4-compatible addresses can all
+                        // end up at an ipv4 address that wasn't checked
+                        !(ip.is_loopback()
+                            || ip.is_unspecified()
+                            || ip.is_multicast()
+                            || (first & 0xfe00) == 0xfc00
+                            || (first & 0xffc0) == 0xfe80
+                            || (first == 0x64 && segments[1] == 0xff9b)
+                            || first == 0x2002
+                            || segments[..6] == [0; 6])
+                    }
+                },
+            }
+        }
+
+        /// Reads `og:` meta tags, falling back to the page's `<title>`
+        /// and description.
+        pub fn parse(url: &str, html: &str) -> LinkPreview {
+            let head = match html.to_ascii_lowercase().find("</head") {
+                Some(end) => &html[..end],
+                None => html,
+            };
+            let mut preview = LinkPreview {
+                url: url.to_string(),
+                ..Default::default()
+            };
+            let mut description = None;
+            for tag in head.split('<').filter(|tag| {
+                tag.get(..4)
+                    .map_or(false, |t| t.eq_ignore_ascii_case("meta"))
+            }) {
+                let key = attribute(tag, "property").or_else(|| attribute(tag, "name"));
+                let Some(content) = attribute(tag, "content") else { continue };
+                match key.as_deref() {
+                    Some("og:title") => preview.title = Some(content),
+                    Some("og:description") => preview.description = Some(content),
+                    Some("og:image") => {
+                        preview.image_url = Url::parse(url)
+                            .and_then(|base| base.join(&content))
+                            .ok()
+                            .and_then(|image| crate::models::sanitize_url(image.as_str()))
+                    }
+                    Some("description") => description = Some(content),
+                    _ => {}
+                }
+            }
+            if preview.title.is_none() {
+                preview.title = title(head);
+            }
+            if preview.description.is_none() {
+                preview.description = description;
+            }
+            preview
+        }
+
+        fn title(head: &str) -> Option<String> {
+            let lower = head.to_ascii_lowercase();
+            let start = lower.find("<title")?;
+            let start = start + lower[start..].find('>')? + 1;
+            let end = start + lower[start..].find("</title")?;
+            let title = decode(head[start..end].trim());
+            (!title.is_empty()).then_some(title)
+        }
+
+        fn attribute(tag: &str, name: &str) -> Option<String> {
+            let lower = tag.to_ascii_lowercase();
+            let mut from = 0;
+            while let Some(found) = lower[from..].find(name) {
+                let start = from + found;
+                from = start + name.len();
+                let preceded_by_space = lower[..start].ends_with(char::is_whitespace);
+                let rest = lower[from..].trim_start();
+                if !preceded_by_space || !rest.starts_with('=') {
+                    continue;
+                }
+                let value_start = tag.len() - rest.len() + 1;
+                let value = tag[value_start..].trim_start();
+                let value = match value.chars().next()? {
+                    quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+                    _ => value
+                        .split(|c: char| c.is_whitespace() || c == '>')
+                        .next()?,
+                };
+                return Some(decode(value.trim()));
+            }
+            None
+        }
+
+        fn decode(text: &str) -> String {
+            text.replace("&quot;", "\"")
+                .replace("&#39;", "'")
+                .replace("&#x27;", "'")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&amp;", "&")
+        }
+    }
+
+    /// Images are decoded and encoded again before they're stored, which
+    /// drops exif and anything else riding along in the original file.
+    pub mod uploads {
+        use crate::AppError;
+        use image::{imageops::FilterType, DynamicImage, ImageFormat};
+        use std::path::Path;
+
+        pub const MAX_BYTES: usize = 5 * 1024 * 1024;
+        const MAX_DIMENSION: u32 = 2048;
+        const THUMBNAIL_SIZE: u32 = 480;
+
+        pub struct Processed {
+            pub filename: String,
+            pub width: u32,
+            pub height: u32,
+        }
+
+        pub fn thumbnail_name(filename: &str) -> String {
+            format!("thumb-{filename}")
+        }
+
+        /// Only names `save` could have made, so a request can't wander
+        /// out of the uploads directory.
+        pub fn is_valid_name(filename: &str) -> bool {
+            let stem = filename.trim_start_matches("thumb-");
+            let Some((id, extension)) = stem.split_once('.') else { return false };
+            !id.is_empty()
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                && matches!(extension, "jpg" | "png")
+        }
+
+        pub fn save(dir: &Path, bytes: &[u8]) -> Result<Processed, AppError> {
+            let format = image::guess_format(bytes).map_err(|_| AppError::InvalidImage)?;
+            let image = image::load_from_memory_with_format(bytes, format)
+                .map_err(|_| AppError::InvalidImage)?;
+            let image = match image.width() > MAX_DIMENSION || image.height() > MAX_DIMENSION {
+                true => image.resize(MAX_DIMENSION, MAX_DIMENSION, FilterType::Lanczos3),
+                false => image,
+            };
+            let (format, extension) = match format {
+                ImageFormat::Png => (ImageFormat::Png, "png"),
+                _ => (ImageFormat::Jpeg, "jpg"),
+            };
+            let filename = format!("{}.{extension}", nanoid::nanoid!());
+            std::fs::create_dir_all(dir)?;
+            write(&image, format, &dir.join(&filename))?;
+            let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+            write(&thumbnail, format, &dir.join(thumbnail_name(&filename)))?;
+            Ok(Processed {
+                filename,
+                width: image.width(),
+                height: image.height(),
+            })
+        }
+
+        fn write(image: &DynamicImage, format: ImageFormat, path: &Path) -> Result<(), AppError> {
+            // jpeg has no alpha channel
+            let result = match format {
+                ImageFormat::Jpeg => image.to_rgb8().save_with_format(path, format),
+                _ => image.save_with_format(path, format),
+            };
+            result.map_err(|err| AppError::Io(err.to_string()))
+        }
+    }
+
+    /// Prometheus metrics, kept in memory and rendered by `/metrics`.
+    pub mod metrics {
+        use axum::http::StatusCode;
+        use std::collections::BTreeMap;
+        use std::fmt::Write;
+        use std::sync::Mutex;
+        use std::time::{Duration, Instant};
+
+        const BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+        #[derive(Default)]
+        struct Histogram {
+            buckets: [u64; BUCKETS.len()],
+            sum: f64,
+            count: u64,
+        }
+
+        impl Histogram {
+            fn observe(&mut self, duration: Duration) {
+                let seconds = duration.as_secs_f64();
+                for (bucket, le) in self.buckets.iter_mut().zip(BUCKETS) {
+                    if seconds <= le {
+                        *bucket += 1;
+                    }
+                }
+                self.sum += seconds;
+                self.count += 1;
+            }
+
+            fn render(&self, out: &mut String, name: &str, labels: &str) {
+                for (bucket, le) in self.buckets.iter().zip(BUCKETS) {
+                    let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{le}\"}} {bucket}");
+                }
+                let _ = writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {}", self.count);
+                let _ = writeln!(out, "{name}_sum{{{labels}}} {}", self.sum);
+                let _ = writeln!(out, "{name}_count{{{labels}}} {}", self.count);
+            }
+        }
+
+        struct Registry {
+            requests: BTreeMap<(String, u16), u64>,
+            request_seconds: BTreeMap<String, Histogram>,
+            query_seconds: BTreeMap<&'static str, Histogram>,
+            scheduled_posts_published: u64,
+        }
+
+        static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+            requests: BTreeMap::new(),
+            request_seconds: BTreeMap::new(),
+            query_seconds: BTreeMap::new(),
+            scheduled_posts_published: 0,
+        });
+
+        pub struct PoolStats {
+            pub size: u32,
+            pub idle: usize,
+            pub max: u32,
+        }
+
+        /// Requests are labelled with the route they matched, `/profile/:name`
+        /// rather than each profile, so the number of series stays fixed.
+        fn route_label(route: Option<&str>, status: StatusCode) -> String {
+            match route {
+                _ if status == StatusCode::NOT_FOUND => "not_found".to_string(),
+                Some(route) => route.to_string(),
+                None => "unmatched".to_string(),
+            }
+        }
+
+        pub fn record_request(route: Option<&str>, status: StatusCode, latency: Duration) {
+            let route = route_label(route, status);
+            let mut registry = REGISTRY.lock().unwrap();
+            *registry
+                .requests
+                .entry((route.clone(), status.as_u16()))
+                .or_default() += 1;
+            registry
+                .request_seconds
+                .entry(route)
+                .or_default()
+                .observe(latency);
+        }
+
+        pub fn record_scheduled_publish() {
+            REGISTRY.lock().unwrap().scheduled_posts_published += 1;
+        }
+
+        pub struct QueryTimer {
+            name: &'static str,
+            start: Instant,
+        }
+
+        /// Records how long the rest of the calling `Database` method takes.
+        pub fn query_timer(name: &'static str) -> QueryTimer {
+            QueryTimer {
+                name,
+                start: Instant::now(),
+            }
+        }
+
+        impl Drop for QueryTimer {
+            fn drop(&mut self) {
+                let mut registry = REGISTRY.lock().unwrap();
+                registry
+                    .query_seconds
+                    .entry(self.name)
+                    .or_default()
+                    .observe(self.start.elapsed());
+            }
+        }
+
+        pub fn render(pool: PoolStats) -> String {
+            let registry = REGISTRY.lock().unwrap();
+            let mut out = String::new();
+            out.push_str("# TYPE http_requests_total counter\n");
+            for ((route, status), count) in &registry.requests {
+                let _ = writeln!(
+                    out,
+                    "http_requests_total{{route=\"{route}\",status=\"{status}\"}} {count}"
+                );
+            }
+            out.push_str("# TYPE http_request_duration_seconds histogram\n");
+            for (route, histogram) in &registry.request_seconds {
+                histogram.render(
+                    &mut out,
+                    "http_request_duration_seconds",
+                    &format!("route=\"{route}\""),
+                );
+            }
+            out.push_str("# TYPE sqlite_query_duration_seconds histogram\n");
+            for (query, histogram) in &registry.query_seconds {
+                histogram.render(
+                    &mut out,
+                    "sqlite_query_duration_seconds",
+                    &format!("query=\"{query}\""),
+                );
+            }
+            out.push_str("# TYPE scheduled_posts_published_total counter\n");
+            let _ = writeln!(
+                out,
+                "scheduled_posts_published_total {}",
+                registry.scheduled_posts_published
+            );
+            out.push_str("# TYPE sqlite_pool_connections gauge\n");
+            let _ = writeln!(
+                out,
+                "sqlite_pool_connections{{state=\"idle\"}} {}",
+                pool.idle
+            );
+            let _ = writeln!(
+                out,
+                "sqlite_pool_connections{{state=\"active\"}} {}",
+                (pool.size as usize).saturating_sub(pool.idle)
+            );
+            out.push_str("# TYPE sqlite_pool_max_connections gauge\n");
+            let _ = writeln!(out, "sqlite_pool_max_connections {}", pool.max);
+            out
+        }
+    }
+
+    /// Command line flags and the environment variables they stand in for.
+    /// `LOGIN_CODE_KEY` and `METRICS_TOKEN` are secrets and have no flag,
+    /// arguments show up in `ps` for anyone on the machine.
+    const FLAGS: [(&str, &str); 11] = [
+        ("--host", "HOST"),
+        ("--port", "PORT"),
+        ("--database-url", "DATABASE_URL"),
+        ("--pool-size", "DATABASE_POOL_SIZE"),
+        ("--cookie-secure", "COOKIE_SECURE"),
+        ("--public-url", "PUBLIC_URL"),
+        ("--drain-timeout", "DRAIN_TIMEOUT_SECS"),
+        ("--log-format", "LOG_FORMAT"),
+        ("--report-threshold", "REPORT_THRESHOLD"),
+        ("--deletion-grace-days", "DELETION_GRACE_DAYS"),
+        ("--uploads-dir", "UPLOADS_DIR"),
+    ];
+
+    /// Settings come from `.env`, then the process environment, then
+    /// command line flags, each one overriding the last.
+    #[derive(Clone)]
+    pub struct Env {
+        pub database_url: String,
+        pub login_code_key: String,
+        pub host: IpAddr,
+        pub port: u16,
+        pub pool_size: u32,
+        pub cookie_secure: bool,
+        pub public_url: String,
+        pub drain_timeout: Duration,
+        pub log_format: LogFormat,
+        pub report_threshold: i64,
+        pub deletion_grace: Duration,
+        pub uploads_dir: PathBuf,
+        pub metrics_token: Option<String>,
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    pub enum LogFormat {
+        Text,
+        Json,
+    }
+
+    impl std::str::FromStr for LogFormat {
+        type Err = ();
+
+        fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+            match s {
+                "text" => Ok(LogFormat::Text),
+                "json" => Ok(LogFormat::Json),
+                _ => Err(()),
+            }
+        }
+    }
+
+    impl Env {
+        pub fn load(flags: HashMap<String, String>) -> Result<Self> {
+            Self::from_vars(&Self::layer(&Self::read(), std::env::vars(), flags))
+        }
+
+        fn layer(
+            file: &str,
+            process: impl IntoIterator<Item = (String, String)>,
+            flags: HashMap<String, String>,
+        ) -> HashMap<String, String> {
+            let mut vars = Self::parse(file);
+            vars.extend(process);
+            vars.extend(flags);
+            vars
+        }
+
+        pub fn read() -> String {
+            std::fs::read_to_string(".env").unwrap_or_default()
+        }
+
+        /// Pulls `--flag value` pairs out of args so commands only see their own arguments.
+        pub fn take_flags(args: &mut Vec<String>) -> HashMap<String, String> {
+            let mut flags = HashMap::new();
+            let mut i = 0;
+            while i < args.len() {
+                match FLAGS.iter().find(|(flag, _)| *flag == args[i]) {
+                    Some((_, key)) if i + 1 < args.len() => {
+                        let value = args.remove(i + 1);
+                        args.remove(i);
+                        flags.insert(key.to_string(), value);
+                    }
+                    _ => i += 1,
+                }
+            }
+            flags
+        }
+
+        pub fn parse(file: &str) -> HashMap<String, String> {
+            file.lines().filter_map(Self::parse_line).collect()
+        }
+
+        fn parse_line(line: &str) -> Option<(String, String)> {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            Some((key.trim().to_string(), Self::parse_value(value.trim())))
+        }
+
+        fn parse_value(value: &str) -> String {
+            for quote in ['"', '\''] {
+                if let Some(value) = value.strip_prefix(quote) {
+                    return match value.find(quote) {
+                        Some(end) => value[..end].to_string(),
+                        None => value.to_string(),
+                    };
+                }
+            }
+            match value.find(" #") {
+                Some(start) => value[..start].trim_end().to_string(),
+                None => value.to_string(),
+            }
+        }
+
+        pub fn from_vars(vars: &HashMap<String, String>) -> Result<Self> {
+            let mut errors = vec![];
+            let database_url = Self::required(vars, "DATABASE_URL", &mut errors);
+            let login_code_key = Self::required(vars, "LOGIN_CODE_KEY", &mut errors);
+            let host = Self::optional(
+                vars,
+                "HOST",
+                IpAddr::from([127, 0, 0, 1]),
+                "an ip address",
+                &mut errors,
+            );
+            let port = Self::optional(vars, "PORT", 9004, "a port number", &mut errors);
+            let pool_size = Self::optional(
+                vars,
+                "DATABASE_POOL_SIZE",
+                5,
+                "a number of connections",
+                &mut errors,
+            );
+            let cookie_secure = Self::optional(
+                vars,
+                "COOKIE_SECURE",
+                cfg!(not(debug_assertions)),
+                "true or false",
+                &mut errors,
+            );
+            let public_url = match vars.get("PUBLIC_URL") {
+                Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+                    url.trim_end_matches('/').to_string()
+                }
+                Some(url) => {
+                    errors.push(format!("PUBLIC_URL={url} is not an http or https url"));
+                    String::default()
+                }
+                None => format!("http://{host}:{port}"),
+            };
+            let drain_timeout = Self::optional(
+                vars,
+                "DRAIN_TIMEOUT_SECS",
+                10,
+                "a number of seconds",
+                &mut errors,
+            );
+            let log_format = Self::optional(
+                vars,
+                "LOG_FORMAT",
+                LogFormat::Text,
+                "text or json",
+                &mut errors,
+            );
+            let report_threshold = Self::optional(
+                vars,
+                "REPORT_THRESHOLD",
+                3,
+                "a number of reports",
+                &mut errors,
+            );
+            let deletion_grace_days: u64 = Self::optional(
+                vars,
+                "DELETION_GRACE_DAYS",
+                30,
+                "a number of days",
+                &mut errors,
+            );
+            let uploads_dir = Self::optional(
+                vars,
+                "UPLOADS_DIR",
+                PathBuf::from("./uploads"),
+                "a directory",
+                &mut errors,
+            );
+            let metrics_token = vars
+                .get("METRICS_TOKEN")
+                .filter(|token| !token.is_empty())
+                .cloned();
+            if pool_size == 0 {
+                errors.push("DATABASE_POOL_SIZE needs to be at least 1".to_string());
+            }
+            if report_threshold < 1 {
+                errors.push("REPORT_THRESHOLD needs to be at least 1".to_string());
+            }
+            if !errors.is_empty() {
+                return Err(AppError::Env(errors));
+            }
+            Ok(Self {
+                database_url,
+                login_code_key,
+                host,
+                port,
+                pool_size,
+                cookie_secure,
+                public_url,
+                drain_timeout: Duration::from_secs(drain_timeout),
+                log_format,
+                report_threshold,
+                deletion_grace: Duration::from_secs(deletion_grace_days * 24 * 60 * 60),
+                uploads_dir,
+                metrics_token,
+            })
+        }
+
+        fn required(vars: &HashMap<String, String>, key: &str, errors: &mut Vec<String>) -> String {
+            match vars.get(key) {
+                Some(value) if !value.is_empty() => value.clone(),
+                _ => {
+                    errors.push(format!("{key} is missing"));
+                    String::default()
+                }
+            }
+        }
+
+        fn optional<T: std::str::FromStr>(
+            vars: &HashMap<String, String>,
+            key: &str,
+            default: T,
+            expected: &str,
+            errors: &mut Vec<String>,
+        ) -> T {
+            match vars.get(key) {
+                Some(value) => value.parse().unwrap_or_else(|_| {
+                    errors.push(format!("{key}={value} is not {expected}"));
+                    default
+                }),
+                None => default,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use axum::headers::HeaderMapExt;
+        use axum::http::{HeaderMap, HeaderValue};
+
+        /// A database and its uploads in a temporary directory that's
+        /// removed when the test is done with it.
+        struct TestDatabase {
+            db: Database,
+            _dir: tempfile::TempDir,
+        }
+
+        impl std::ops::Deref for TestDatabase {
+            type Target = Database;
+
+            fn deref(&self) -> &Database {
+                &self.db
+            }
+        }
+
+        impl std::ops::DerefMut for TestDatabase {
+            fn deref_mut(&mut self) -> &mut Database {
+                &mut self.db
+            }
+        }
+
+        async fn database() -> TestDatabase {
+            let db = unmigrated_database().await;
+            db.migrate().await.unwrap();
+            db
+        }
+
+        async fn unmigrated_database() -> TestDatabase {
+            let dir = tempfile::tempdir().unwrap();
+            let filename = dir.path().join("yallpost.db");
+            let uploads = dir.path().join("uploads");
+            let env = Env::from_vars(&HashMap::from([
+                (
+                    "DATABASE_URL".to_string(),
+                    format!("sqlite://{}", filename.display()),
+                ),
+                ("LOGIN_CODE_KEY".to_string(), "test".to_string()),
+                ("UPLOADS_DIR".to_string(), uploads.display().to_string()),
+            ]))
+            .unwrap();
+            TestDatabase {
+                db: Database::new(&env).await,
+                _dir: dir,
+            }
+        }
+
+        fn cookie(session: &Session) -> Cookie {
+            let mut headers = HeaderMap::new();
+            let value = format!("id={}", session.identifier);
+            headers.insert(header::COOKIE, HeaderValue::from_str(&value).unwrap());
+            headers.typed_get::<Cookie>().unwrap()
+        }
+
+        #[test]
+        fn env_files_parse_like_a_shell_would() {
+            let file = r#"
+                # a comment
+                DATABASE_URL=sqlite://db.sqlite3?mode=rwc
+                export LOGIN_CODE_KEY="secret # not a comment"
+                PUBLIC_URL='https://yall.example' # the real one
+                LOG_FORMAT=json # or text
+                UPLOADS_DIR=./up#loads
+                PORT=9004
+            "#;
+            let vars = Env::layer(
+                file,
+                [("PORT".to_string(), "9005".to_string())],
+                HashMap::from([("HOST".to_string(), "0.0.0.0".to_string())]),
+            );
+            assert_eq!(vars["DATABASE_URL"], "sqlite://db.sqlite3?mode=rwc");
+            assert_eq!(vars["LOGIN_CODE_KEY"], "secret # not a comment");
+            assert_eq!(vars["PUBLIC_URL"], "https://yall.example");
+            assert_eq!(vars["LOG_FORMAT"], "json");
+            assert_eq!(vars["UPLOADS_DIR"], "./up#loads");
+            assert_eq!(vars["PORT"], "9005");
+            let env = Env::from_vars(&vars).unwrap();
+            assert_eq!(env.port, 9005);
+            assert_eq!(env.host, IpAddr::from([0, 0, 0, 0]));
+            assert!(matches!(env.log_format, LogFormat::Json));
+            assert_eq!(Env::parse_value("a=b=c"), "a=b=c");
+            assert_eq!(Env::parse_value("\"unterminated"), "unterminated");
+        }
+
+        #[test]
+        fn env_lists_every_bad_value() {
+            let vars = HashMap::from([
+                (
+                    "DATABASE_URL".to_string(),
+                    "sqlite://db.sqlite3".to_string(),
+                ),
+                ("PORT".to_string(), "ninety".to_string()),
+                ("DATABASE_POOL_SIZE".to_string(), "0".to_string()),
+                ("PUBLIC_URL".to_string(), "yall.example".to_string()),
+                ("REPORT_THRESHOLD".to_string(), "0".to_string()),
+            ]);
+            let Err(AppError::Env(errors)) = Env::from_vars(&vars) else { panic!("expected env errors") };
+            assert_eq!(
+                errors,
+                vec![
+                    "LOGIN_CODE_KEY is missing",
+                    "PORT=ninety is not a port number",
+                    "PUBLIC_URL=yall.example is not an http or https url",
+                    "DATABASE_POOL_SIZE needs to be at least 1",
+                    "REPORT_THRESHOLD needs to be at least 1",
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn migration_status_leaves_a_new_database_alone() {
+            let db = unmigrated_database().await;
+            let migrations = db.migration_status().await.unwrap();
+            assert!(!migrations.is_empty());
+            assert!(migrations
+                .iter()
+                .all(|m| m.state == MigrationState::Pending));
+            let tables = sqlx::query("select name from sqlite_master where type = 'table'")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+            assert!(tables.is_empty());
+        }
+
+        #[tokio::test]
+        async fn index_does_not_render_login_codes() {
+            let db = database().await;
+            let account = db.insert_account("yall".to_string()).await.unwrap();
+            let login_code = db.rotate_login_code(account.id).await.unwrap();
+            let session = db.insert_session(account.id).await.unwrap();
+            db.insert_post("first".to_string(), account, None, None)
+                .await
+                .unwrap();
+            let server_props = server_props(&db, &cookie(&session)).await;
+            let html = render_page(
+                include_str!("../index.html"),
+                "https://yall.example/",
+                server_props,
+            );
+            assert!(html.contains(r#"<link rel="canonical" href="https://yall.example/" />"#));
+            assert!(html.contains("yall"));
+            assert!(!html.contains(&login_code));
+            assert!(!html.contains("login_code"));
+        }
+
+        #[tokio::test]
+        async fn profile_pages_render_their_fields() {
+            let db = database().await;
+            let account = db.insert_account("yall".to_string()).await.unwrap();
+            let session = db.insert_session(account.id).await.unwrap();
+            let fields = ProfileFields {
+                bio: Some("posting since forever".to_string()),
+                website: Some("https://yall.example".to_string()),
+                pronouns: Some("they/them".to_string()),
+            };
+            db.set_profile_fields(account.id, fields).await.unwrap();
+            let server_props = profile_props(&db, &cookie(&session), account).await;
+            let html = render_page(
+                include_str!("../index.html"),
+                "https://yall.example/profile/yall",
+                server_props,
+            );
+            // the props are in the head too, only look at what was rendered
+            let body = &html[html.find("<body>").unwrap()..];
+            assert!(body.contains("posting since forever"));
+            assert!(body.contains("https://yall.example"));
+            assert!(body.contains("they/them"));
+        }
+
+        #[tokio::test]
+        async fn requested_deletion_hides_until_restored_or_purged() {
+            let db = database().await;
+            let (leaving, staying, session) = accounts_with_activity(&db).await;
+            db.request_account_deletion(leaving.id, DeletionMode::Remove)
+                .await
+                .unwrap();
+            assert!(db.session_by_identifer(&session.identifier).await.is_err());
+            assert_eq!(db.posts(None).await.unwrap().len(), 1);
+            assert!(db
+                .restore_account(leaving.id, Duration::from_secs(60))
+                .await
+                .unwrap());
+            assert_eq!(db.posts(None).await.unwrap().len(), 2);
+            db.request_account_deletion(leaving.id, DeletionMode::Remove)
+                .await
+                .unwrap();
+            assert!(matches!(
+                db.restore_account(leaving.id, Duration::ZERO).await,
+                Err(AppError::NotFound)
+            ));
+            assert_eq!(
+                db.purge_deleted_accounts(Duration::from_secs(60))
+                    .await
+                    .unwrap(),
+                0
+            );
+            assert_eq!(db.purge_deleted_accounts(Duration::ZERO).await.unwrap(), 1);
+            assert!(db.account_by_id(leaving.id).await.is_err());
+            assert!(db.account_by_id(staying.id).await.is_ok());
+        }
+
+        #[tokio::test]
+        async fn server_fn_bodies_over_the_cap_are_turned_away() {
+            assert!(read_server_fn_body(Body::from(vec![0u8; 1024]))
+                .await
+                .is_ok());
+            let too_big = Body::from(vec![0u8; MAX_SERVER_FN_BODY + 1]);
+            assert_eq!(
+                read_server_fn_body(too_big).await.err(),
+                Some(StatusCode::PAYLOAD_TOO_LARGE)
+            );
+        }
+
+        #[tokio::test]
+        async fn pending_deletion_hides_posts_by_id_and_from_comment_counts() {
+            let db = database().await;
+            let (leaving, staying, _) = accounts_with_activity(&db).await;
+            let posts = db.posts(None).await.unwrap();
+            let leaving_post = posts.iter().find(|p| p.account_id == leaving.id).unwrap();
+            let staying_post = posts.iter().find(|p| p.account_id == staying.id).unwrap();
+            assert_eq!(staying_post.comment_count, 1);
+            db.request_account_deletion(leaving.id, DeletionMode::Remove)
+                .await
+                .unwrap();
+            assert!(matches!(
+                db.post_by_id(leaving_post.id, Some(staying.clone())).await,
+                Err(AppError::NotFound)
+            ));
+            assert!(matches!(
+                db.insert_like(staying.id, leaving_post.id).await,
+                Err(AppError::NotFound)
+            ));
+            let seen = db.post_by_id(staying_post.id, None).await.unwrap();
+            assert_eq!(seen.comment_count, 0);
+        }
+
+        #[tokio::test]
+        async fn scheduled_posts_stay_with_their_author_until_published() {
+            let db = database().await;
+            let author = db.insert_account("author".to_string()).await.unwrap();
+            let reader = db.insert_account("reader".to_string()).await.unwrap();
+            let later = Database::now() as i64 + 60 * 60;
+            let post = db
+                .insert_post("later".to_string(), author.clone(), Some(later), None)
+                .await
+                .unwrap();
+            assert!(db.posts(Some(&reader)).await.unwrap().is_empty());
+            assert_eq!(db.posts(Some(&author)).await.unwrap().len(), 1);
+            assert!(db.post_by_id(post.id, Some(reader.clone())).await.is_err());
+            assert!(db.post_by_id(post.id, Some(author.clone())).await.is_ok());
+            assert!(db.insert_like(reader.id, post.id).await.is_err());
+            assert!(db
+                .insert_comment(post.id, reader.id, "early".to_string())
+                .await
+                .is_err());
+            assert!(db.set_bookmarked(reader.id, post.id, true).await.is_err());
+            assert!(db
+                .comments_by_post_id(post.id, Some(&reader))
+                .await
+                .is_err());
+            assert!(db.publish_due_posts().await.unwrap().is_empty());
+            let updated = db
+                .update_scheduled_post(author.clone(), post.id, "edited".to_string(), None)
+                .await
+                .unwrap();
+            assert_eq!(updated.body, "edited");
+            assert_eq!(updated.publish_at, Some(later));
+            sqlx::query!(
+                "update posts set publish_at = ? where id = ?",
+                later - 2 * 60 * 60,
+                post.id
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+            let published = db.publish_due_posts().await.unwrap();
+            assert_eq!(published.len(), 1);
+            assert_eq!(published[0].id, post.id);
+            assert_eq!(db.posts(Some(&reader)).await.unwrap().len(), 1);
+            db.insert_like(reader.id, post.id).await.unwrap();
+            assert!(db.cancel_scheduled_post(author.id, post.id).await.is_err());
+        }
+
+        #[tokio::test]
+        async fn attachments_are_stored_shown_and_removed() {
+            let db = database().await;
+            let account = db.insert_account("yall".to_string()).await.unwrap();
+            let mut png = std::io::Cursor::new(vec![]);
+            image::RgbaImage::new(4096, 100)
+                .write_to(&mut png, image::ImageOutputFormat::Png)
+                .unwrap();
+            let png = png.into_inner();
+            let attachment = db.insert_attachment(account.id, png.clone()).await.unwrap();
+            let stray = db.insert_attachment(account.id, png).await.unwrap();
+            assert_eq!(db.upload_count_since(account.id, 0.0).await.unwrap(), 2);
+            assert_eq!((attachment.width, attachment.height), (2048, 50));
+            assert!(db
+                .insert_attachment(account.id, vec![1, 2, 3])
+                .await
+                .is_err());
+            let post = db
+                .insert_post_with(
+                    "look".to_string(),
+                    account.clone(),
+                    None,
+                    None,
+                    None,
+                    &[attachment.id],
+                )
+                .await
+                .unwrap();
+            let post = db.post_by_id(post.id, None).await.unwrap();
+            assert_eq!(post.attachment_names(), vec![attachment.filename.as_str()]);
+            let stray_path = db.upload_path(&stray.filename).unwrap();
+            assert!(stray_path.exists());
+            assert_eq!(
+                db.purge_unattached_uploads(Duration::ZERO).await.unwrap(),
+                1
+            );
+            assert!(!stray_path.exists());
+            let path = db.upload_path(&attachment.filename).unwrap();
+            assert!(path.exists());
+            assert!(db.upload_path("../yallpost.db").is_none());
+            db.delete_account(account.id, DeletionMode::Remove)
+                .await
+                .unwrap();
+            assert!(!path.exists());
+        }
+
+        #[tokio::test]
+        async fn poll_results_show_after_voting() {
+            let db = database().await;
+            let author = db.insert_account("author".to_string()).await.unwrap();
+            let voter = db.insert_account("voter".to_string()).await.unwrap();
+            let poll = NewPoll {
+                options: vec!["tabs".to_string(), " spaces ".to_string()],
+                closes_in: 60 * 60,
+            };
+            let post = db
+                .insert_post_with(
+                    "tabs or spaces".to_string(),
+                    author.clone(),
+                    None,
+                    None,
+                    Some(&poll),
+                    &[],
+                )
+                .await
+                .unwrap();
+            let before = db.post_by_id(post.id, Some(voter.clone())).await.unwrap();
+            let options = before.poll_options();
+            assert_eq!(options.len(), 2);
+            assert_eq!(options[1].body, "spaces");
+            assert!(options.iter().all(|option| option.votes.is_none()));
+            db.vote(voter.id, post.id, options[1].id).await.unwrap();
+            assert!(matches!(
+                db.vote(voter.id, post.id, options[0].id).await,
+                Err(AppError::DatabaseUniqueIndex)
+            ));
+            assert!(matches!(
+                db.vote(voter.id, post.id, options[0].id + 100).await,
+                Err(AppError::NotFound)
+            ));
+            let after = db.post_by_id(post.id, Some(voter.clone())).await.unwrap();
+            assert_eq!(after.poll_vote, Some(options[1].id));
+            let votes: Vec<_> = after.poll_options().iter().map(|o| o.votes).collect();
+            assert_eq!(votes, vec![Some(0), Some(1)]);
+            let author_view = db.post_by_id(post.id, Some(author)).await.unwrap();
+            assert!(author_view.poll_options().iter().all(|o| o.votes.is_none()));
+        }
+
+        #[tokio::test]
+        async fn blocked_accounts_cant_interact_with_the_blockers_posts() {
+            let db = database().await;
+            let author = db.insert_account("author".to_string()).await.unwrap();
+            let blocked = db.insert_account("blocked".to_string()).await.unwrap();
+            let poll = NewPoll {
+                options: vec!["yes".to_string(), "no".to_string()],
+                closes_in: 60 * 60,
+            };
+            let post = db
+                .insert_post_with(
+                    "poll".to_string(),
+                    author.clone(),
+                    None,
+                    None,
+                    Some(&poll),
+                    &[],
+                )
+                .await
+                .unwrap();
+            let option_id = post.poll_options()[0].id;
+            db.set_blocked(author.id, blocked.id, true).await.unwrap();
+            assert!(matches!(
+                db.insert_like(blocked.id, post.id).await,
+                Err(AppError::Forbidden)
+            ));
+            assert!(matches!(
+                db.insert_comment(post.id, blocked.id, "hi".to_string())
+                    .await,
+                Err(AppError::Forbidden)
+            ));
+            assert!(matches!(
+                db.vote(blocked.id, post.id, option_id).await,
+                Err(AppError::Forbidden)
+            ));
+            assert!(matches!(
+                db.set_reposted(blocked.id, post.id, true).await,
+                Err(AppError::Forbidden)
+            ));
+            assert!(matches!(
+                db.insert_post("look".to_string(), blocked.clone(), None, Some(post.id))
+                    .await,
+                Err(AppError::Forbidden)
+            ));
+            db.set_reposted(blocked.id, post.id, false).await.unwrap();
+            db.set_blocked(author.id, blocked.id, false).await.unwrap();
+            db.insert_like(blocked.id, post.id).await.unwrap();
+            db.set_reposted(blocked.id, post.id, true).await.unwrap();
+        }
+
+        #[tokio::test]
+        async fn scheduled_polls_move_with_the_post() {
+            let db = database().await;
+            let author = db.insert_account("author".to_string()).await.unwrap();
+            let later = Database::now() as i64 + 60 * 60;
+            let poll = NewPoll {
+                options: vec!["yes".to_string(), "no".to_string()],
+                closes_in: 60 * 60,
+            };
+            let post = db
+                .insert_post_with(
+                    "later".to_string(),
+                    author.clone(),
+                    Some(later),
+                    None,
+                    Some(&poll),
+                    &[],
+                )
+                .await
+                .unwrap();
+            assert_eq!(post.poll_closes_at, Some(later + 60 * 60));
+            let post = db
+                .update_scheduled_post(author, post.id, "later".to_string(), Some(later + 600))
+                .await
+                .unwrap();
+            assert_eq!(post.poll_closes_at, Some(later + 600 + 60 * 60));
+        }
+
+        #[tokio::test]
+        async fn reposts_and_quotes_are_counted_and_attributed() {
+            let db = database().await;
+            let author = db.insert_account("author".to_string()).await.unwrap();
+            let fan = db.insert_account("fan".to_string()).await.unwrap();
+            let post = db
+                .insert_post("original".to_string(), author.clone(), None, None)
+                .await
+                .unwrap();
+            db.set_reposted(fan.id, post.id, true).await.unwrap();
+            db.set_reposted(fan.id, post.id, true).await.unwrap();
+            let quote = db
+                .insert_post("so true".to_string(), fan.clone(), None, Some(post.id))
+                .await
+                .unwrap();
+            assert_eq!(quote.quoted_body.as_deref(), Some("original"));
+            assert_eq!(quote.quoted_account_name.as_deref(), Some("author"));
+            let reposted = db.post_by_id(post.id, Some(fan.clone())).await.unwrap();
+            assert_eq!(reposted.repost_count, 1);
+            assert_eq!(reposted.quote_count, 1);
+            assert_eq!(reposted.reposted_by_current_account, Some(fan.id));
+            assert_eq!(reposted.reposted_by.as_deref(), Some("fan"));
+            let posts = db.posts(None).await.unwrap();
+            assert_eq!(posts.len(), 2);
+            db.moderate_delete_post(author.id, post.id).await.unwrap();
+            let quote = db.post_by_id(quote.id, None).await.unwrap();
+            assert_eq!(quote.quoted_post_id, None);
+            assert_eq!(quote.quoted_body, None);
+        }
+
+        #[tokio::test]
+        async fn hidden_accounts_drop_out_of_reposts_and_quotes() {
+            let db = database().await;
+            let reader = db.insert_account("reader".to_string()).await.unwrap();
+            let author = db.insert_account("author".to_string()).await.unwrap();
+            let quoter = db.insert_account("quoter".to_string()).await.unwrap();
+            let muted = db.insert_account("muted".to_string()).await.unwrap();
+            let leaving = db.insert_account("leaving".to_string()).await.unwrap();
+            let post = db
+                .insert_post("original".to_string(), author.clone(), None, None)
+                .await
+                .unwrap();
+            db.set_reposted(leaving.id, post.id, true).await.unwrap();
+            db.set_reposted(muted.id, post.id, true).await.unwrap();
+            db.set_muted(reader.id, muted.id, true).await.unwrap();
+            let seen = db.post_by_id(post.id, Some(reader.clone())).await.unwrap();
+            assert_eq!(seen.repost_count, 1);
+            assert_eq!(seen.reposted_by.as_deref(), Some("leaving"));
+            db.request_account_deletion(leaving.id, DeletionMode::Remove)
+                .await
+                .unwrap();
+            let seen = db.post_by_id(post.id, Some(reader.clone())).await.unwrap();
+            assert_eq!(seen.repost_count, 0);
+            assert_eq!(seen.reposted_by, None);
+            let seen = db.post_by_id(post.id, None).await.unwrap();
+            assert_eq!(seen.reposted_by.as_deref(), Some("muted"));
+
+            let quote = db
+                .insert_post("look".to_string(), quoter.clone(), None, Some(post.id))
+                .await
+                .unwrap();
+            db.set_bookmarked(reader.id, quote.id, true).await.unwrap();
+            db.set_blocked(reader.id, author.id, true).await.unwrap();
+            let feed = db.posts(Some(&reader)).await.unwrap();
+            let seen = feed.iter().find(|p| p.id == quote.id).unwrap();
+            assert_eq!(seen.quoted_body, None);
+            assert_eq!(seen.quoted_account_name, None);
+            let saved = db.bookmarked_posts(&reader, None).await.unwrap();
+            assert_eq!(saved.posts[0].quoted_body, None);
+            let seen = db.post_by_id(quote.id, None).await.unwrap();
+            assert_eq!(seen.quoted_body.as_deref(), Some("original"));
+            for reporter in [&reader, &quoter, &muted] {
+                db.insert_report(
+                    reporter.id,
+                    &ReportTarget::Post(post.id),
+                    ReportReason::Spam,
+                )
+                .await
+                .unwrap();
+            }
+            let seen = db.post_by_id(quote.id, None).await.unwrap();
+            assert_eq!(seen.quoted_body, None);
+        }
+
+        #[tokio::test]
+        async fn reports_hide_posts_at_the_threshold_until_resolved() {
+            let db = database().await;
+            let author = db.insert_account("author".to_string()).await.unwrap();
+            let moderator = db.insert_account("moderator".to_string()).await.unwrap();
+            let mut reporters = vec![];
+            for name in ["first", "second", "third"] {
+                reporters.push(db.insert_account(name.to_string()).await.unwrap());
+            }
+            let dismissed = db
+                .insert_post("fine actually".to_string(), author.clone(), None, None)
+                .await
+                .unwrap();
+            let removed = db
+                .insert_post("spam".to_string(), author.clone(), None, None)
+                .await
+                .unwrap();
+            let target = ReportTarget::Post(dismissed.id);
+            db.insert_report(reporters[0].id, &target, ReportReason::Spam)
+                .await
+                .unwrap();
+            assert!(matches!(
+                db.insert_report(reporters[0].id, &target, ReportReason::Harassment)
+                    .await,
+                Err(AppError::DatabaseUniqueIndex)
+            ));
+            assert!(matches!(
+                db.insert_report(
+                    reporters[0].id,
+                    &ReportTarget::Post(removed.id + 100),
+                    ReportReason::Spam
+                )
+                .await,
+                Err(AppError::NotFound)
+            ));
+            db.insert_report(reporters[1].id, &target, ReportReason::Spam)
+                .await
+                .unwrap();
+            let in_feed = |posts: &[Post], id: i64| posts.iter().any(|post| post.id == id);
+            assert!(in_feed(&db.posts(None).await.unwrap(), dismissed.id));
+            db.insert_report(reporters[2].id, &target, ReportReason::Harassment)
+                .await
+                .unwrap();
+            assert!(!in_feed(&db.posts(None).await.unwrap(), dismissed.id));
+            db.insert_report(
+                reporters[0].id,
+                &ReportTarget::Post(removed.id),
+                ReportReason::Spam,
+            )
+            .await
+            .unwrap();
+            let queue = db.report_queue().await.unwrap();
+            assert_eq!(queue.len(), 2);
+            assert_eq!(queue[0].target_id, dismissed.id);
+            assert_eq!(queue[0].report_count, 3);
+            assert!(queue[0].reasons.contains("spam") && queue[0].reasons.contains("harassment"));
+
+            db.resolve_reports(moderator.id, &target, ReportResolution::Dismiss)
+                .await
+                .unwrap();
+            assert!(in_feed(&db.posts(None).await.unwrap(), dismissed.id));
+            assert!(matches!(
+                db.resolve_reports(moderator.id, &target, ReportResolution::Dismiss)
+                    .await,
+                Err(AppError::NotFound)
+            ));
+            db.resolve_reports(
+                moderator.id,
+                &ReportTarget::Post(removed.id),
+                ReportResolution::Action,
+            )
+            .await
+            .unwrap();
+            assert!(matches!(
+                db.post_by_id(removed.id, None).await,
+                Err(AppError::NotFound)
+            ));
+            assert!(db.report_queue().await.unwrap().is_empty());
+            let actions = db.moderation_actions().await.unwrap();
+            assert!(actions
+                .iter()
+                .any(|action| action.action == "dismiss" && action.target_id == dismissed.id));
+        }
+
+        #[tokio::test]
+        async fn blocked_and_muted_authors_drop_out_of_posts_and_comments() {
+            let db = database().await;
+            let reader = db.insert_account("reader".to_string()).await.unwrap();
+            let author = db.insert_account("author".to_string()).await.unwrap();
+            let blocked = db.insert_account("blocked".to_string()).await.unwrap();
+            let muted = db.insert_account("muted".to_string()).await.unwrap();
+            let mut posts = vec![];
+            for account in [&author, &blocked, &muted] {
+                let post = db
+                    .insert_post(
+                        format!("from {}", account.name),
+                        account.clone(),
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap();
+                posts.push(post);
+                db.insert_comment(posts[0].id, account.id, format!("{} says hi", account.name))
+                    .await
+                    .unwrap();
+            }
+            db.set_blocked(reader.id, blocked.id, true).await.unwrap();
+            db.set_muted(reader.id, muted.id, true).await.unwrap();
+            let bodies =
+                |posts: Vec<Post>| posts.into_iter().map(|post| post.body).collect::<Vec<_>>();
+            assert_eq!(
+                bodies(db.posts(Some(&reader)).await.unwrap()),
+                vec!["from author"]
+            );
+            assert_eq!(db.posts(None).await.unwrap().len(), 3);
+            let comments = db
+                .comments_by_post_id(posts[0].id, Some(&reader))
+                .await
+                .unwrap();
+            assert_eq!(comments.len(), 1);
+            assert_eq!(comments[0].body, "author says hi");
+            assert_eq!(
+                db.comments_by_post_id(posts[0].id, None)
+                    .await
+                    .unwrap()
+                    .len(),
+                3
+            );
+
+            db.set_blocked(author.id, reader.id, true).await.unwrap();
+            assert!(db
+                .blocked_by_post_author(posts[0].id, reader.id)
+                .await
+                .unwrap());
+            assert!(!db
+                .blocked_by_post_author(posts[0].id, muted.id)
+                .await
+                .unwrap());
+            assert!(!db
+                .blocked_by_post_author(posts[2].id, reader.id)
+                .await
+                .unwrap());
+            db.set_blocked(author.id, reader.id, false).await.unwrap();
+            assert!(!db
+                .blocked_by_post_author(posts[0].id, reader.id)
+                .await
+                .unwrap());
+            db.set_muted(reader.id, muted.id, false).await.unwrap();
+            assert_eq!(db.posts(Some(&reader)).await.unwrap().len(), 2);
+        }
+
+        #[tokio::test]
+        async fn drafts_belong_to_their_account() {
+            let db = database().await;
+            let writer = db.insert_account("writer".to_string()).await.unwrap();
+            let other = db.insert_account("other".to_string()).await.unwrap();
+            let draft = db
+                .save_draft(writer.id, None, "first".to_string())
+                .await
+                .unwrap();
+            let saved = db
+                .save_draft(writer.id, Some(draft.id), "second".to_string())
+                .await
+                .unwrap();
+            assert_eq!(saved.id, draft.id);
+            assert_eq!(saved.body, "second");
+            assert!(matches!(
+                db.save_draft(other.id, Some(draft.id), "mine now".to_string())
+                    .await,
+                Err(AppError::NotFound)
+            ));
+            assert!(matches!(
+                db.delete_draft(other.id, draft.id).await,
+                Err(AppError::NotFound)
+            ));
+            let drafts = db.drafts(writer.id).await.unwrap();
+            assert_eq!(drafts.len(), 1);
+            assert_eq!(drafts[0].body, "second");
+            assert!(db.drafts(other.id).await.unwrap().is_empty());
+            db.delete_draft(writer.id, draft.id).await.unwrap();
+            assert!(db.drafts(writer.id).await.unwrap().is_empty());
+            assert!(matches!(
+                db.delete_draft(writer.id, draft.id).await,
+                Err(AppError::NotFound)
+            ));
+        }
+
+        #[tokio::test]
+        async fn bookmarks_are_private_and_paginated() {
+            let db = database().await;
+            let reader = db.insert_account("reader".to_string()).await.unwrap();
+            let other = db.insert_account("other".to_string()).await.unwrap();
+            let muted = db.insert_account("muted".to_string()).await.unwrap();
+            for i in 0..SAVED_PAGE_SIZE + 1 {
+                let post = db
+                    .insert_post(format!("post {i}"), other.clone(), None, None)
+                    .await
+                    .unwrap();
+                db.set_bookmarked(reader.id, post.id, true).await.unwrap();
+                if i == 5 {
+                    let post = db
+                        .insert_post("muted post".to_string(), muted.clone(), None, None)
+                        .await
+                        .unwrap();
+                    db.set_bookmarked(reader.id, post.id, true).await.unwrap();
+                }
+            }
+            db.set_muted(reader.id, muted.id, true).await.unwrap();
+            let first = db.bookmarked_posts(&reader, None).await.unwrap();
+            assert_eq!(first.posts.len() as i64, SAVED_PAGE_SIZE);
+            assert!(first.has_more);
+            assert_eq!(first.posts[0].body, format!("post {SAVED_PAGE_SIZE}"));
+            assert!(first
+                .posts
+                .iter()
+                .all(|post| post.bookmarked_by_current_account == Some(reader.id)));
+            let before = first.posts.last().unwrap().bookmark_id;
+            db.set_bookmarked(reader.id, first.posts[0].id, false)
+                .await
+                .unwrap();
+            let second = db.bookmarked_posts(&reader, before).await.unwrap();
+            assert_eq!(second.posts.len(), 1);
+            assert_eq!(second.posts[0].body, "post 0");
+            assert!(!second.has_more);
+            db.set_bookmarked(reader.id, second.posts[0].id, false)
+                .await
+                .unwrap();
+            assert!(db
+                .bookmarked_posts(&reader, before)
+                .await
+                .unwrap()
+                .posts
+                .is_empty());
+            let seen_by_other = db
+                .post_by_id(first.posts[1].id, Some(other.clone()))
+                .await
+                .unwrap();
+            assert_eq!(seen_by_other.bookmarked_by_current_account, None);
+            assert!(db
+                .bookmarked_posts(&other, None)
+                .await
+                .unwrap()
+                .posts
+                .is_empty());
+        }
+
+        #[test]
+        fn bodies_are_cleaned_and_limited() {
+            use crate::validation::{clean_body, validate_body, COMMENT_BODY, POST_BODY};
+            assert_eq!(clean_body("  hi\u{0}\u{202e} there\r\n "), "hi there");
+            assert!(validate_body(" \n\t ", POST_BODY).message().is_some());
+            assert!(validate_body("hello", POST_BODY).message().is_none());
+            let long = "a".repeat(COMMENT_BODY.max_chars + 1);
+            assert!(validate_body(&long, COMMENT_BODY).message().is_some());
+            assert!(validate_body(&long, POST_BODY).message().is_none());
+            let tall = "a\n".repeat(POST_BODY.max_lines + 1);
+            assert_eq!(
+                validate_body(&tall, POST_BODY).lines,
+                POST_BODY.max_lines + 1
+            );
+            assert!(validate_body(&tall, POST_BODY).message().is_some());
+        }
+
+        #[tokio::test]
+        async fn old_handles_redirect_until_they_expire() {
+            let db = database().await;
+            let account = db.insert_account("alice".to_string()).await.unwrap();
+            db.insert_account("bob".to_string()).await.unwrap();
+            let account = db
+                .set_display_name(account.id, Some("Alice A.".to_string()))
+                .await
+                .unwrap();
+            assert_eq!(PublicAccount::from(account.clone()).label(), "Alice A.");
+            let renamed = db
+                .change_name(account.id, "alicia".to_string())
+                .await
+                .unwrap();
+            assert_eq!(renamed.display_name.as_deref(), Some("Alice A."));
+            assert_eq!(db.account_by_handle("alice").await.unwrap().id, account.id);
+            assert!(!db.name_available("a1ice").await.unwrap());
+            assert!(matches!(
+                db.insert_account("alice".to_string()).await,
+                Err(AppError::DatabaseUniqueIndex)
+            ));
+            assert!(matches!(
+                db.change_name(account.id, "alison".to_string()).await,
+                Err(AppError::NameChangeTooSoon)
+            ));
+            sqlx::query!("update handle_redirects set expires_at = 0")
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            sqlx::query!("update accounts set name_changed_at = 0")
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            assert!(db.account_by_handle("alice").await.is_err());
+            assert!(matches!(
+                db.change_name(account.id, "b0b".to_string()).await,
+                Err(AppError::DatabaseUniqueIndex)
+            ));
+            db.insert_account("alice".to_string()).await.unwrap();
+        }
+
+        #[tokio::test]
+        async fn profile_fields_are_cleaned_and_links_sanitised() {
+            use crate::{validate_profile_fields, ApiError};
+            let fields = validate_profile_fields(ProfileFields {
+                bio: Some("  hi\u{202e} there ".to_string()),
+                website: Some(" https://yallpost.com ".to_string()),
+                pronouns: Some(" ".to_string()),
+            })
+            .unwrap();
+            assert_eq!(fields.bio.as_deref(), Some("hi there"));
+            assert_eq!(fields.website.as_deref(), Some("https://yallpost.com"));
+            assert_eq!(fields.pronouns, None);
+            let rejected = validate_profile_fields(ProfileFields {
+                website: Some("javascript:alert(1)".to_string()),
+                ..Default::default()
+            });
+            assert!(matches!(rejected, Err(ApiError::Validation(_))));
+            for url in [
+                "https://",
+                "http:///path",
+                "https://:443",
+                "https://me@/",
+                "https://[]/",
+            ] {
+                assert_eq!(models::sanitize_url(url), None, "{url}");
+            }
+            for url in [
+                "HTTPS://Yall.example/a?b",
+                "http://[::1]:8080/",
+                "https://me@yall.example",
+            ] {
+                assert_eq!(models::sanitize_url(url).as_deref(), Some(url));
+            }
+            let db = database().await;
+            let account = db.insert_account("yall".to_string()).await.unwrap();
+            db.set_profile_fields(account.id, fields.clone())
+                .await
+                .unwrap();
+            assert_eq!(db.profile_fields(account.id).await.unwrap(), fields);
+        }
+
+        #[tokio::test]
+        async fn lookalike_and_reserved_names_are_rejected() {
+            use crate::{is_reserved_name, name_skeleton, validate_name, SignupNameState};
+            assert_eq!(name_skeleton("Rn.B0b_1"), name_skeleton("mbobl"));
+            assert!(validate_name(&"y.all_post2".to_string()).is_valid());
+            assert!(!validate_name(&"_yall".to_string()).is_valid());
+            assert!(!validate_name(&"ya..ll".to_string()).is_valid());
+            assert_eq!(
+                validate_name(&"Adm1n".to_string()).is_not_reserved,
+                SignupNameState::Invalid
+            );
+            assert!(is_reserved_name("Ad M1n"));
+            assert!(is_reserved_name("Yall.Post"));
+            assert!(!is_reserved_name("Alice A."));
+            let db = database().await;
+            db.insert_account("alice".to_string()).await.unwrap();
+            assert!(!db.name_available("a1ice").await.unwrap());
+            assert!(db.name_available("bob").await.unwrap());
+            assert!(matches!(
+                db.insert_account("A.lice".to_string()).await,
+                Err(AppError::DatabaseUniqueIndex)
+            ));
+        }
+
+        /// Serves the same page for every url and counts how often it's asked.
+        #[derive(Debug, Default)]
+        struct StandInFetcher {
+            fetches: std::sync::atomic::AtomicUsize,
+        }
+
+        impl previews::PreviewFetcher for StandInFetcher {
+            fn fetch<'a>(
+                &'a self,
+                _url: &'a str,
+            ) -> futures_util::future::BoxFuture<'a, Result<String>> {
+                self.fetches
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Box::pin(async {
+                    Ok(r#"<html><head>
+                        <title>Fallback</title>
+                        <meta property="og:title" content="Tom &amp; Jerry">
+                        <meta name="description" content='A cat and a mouse'>
+                        <meta property="og:image" content="/cover.png">
+                    </head><body><meta property="og:title" content="body"></body></html>"#
+                        .to_string())
+                })
+            }
+        }
+
+        #[tokio::test]
+        async fn link_previews_are_parsed_and_cached() {
+            let fetcher = Arc::new(StandInFetcher::default());
+            let mut db = database().await;
+            db.previews = fetcher.clone();
+            let account = db.insert_account("yall".to_string()).await.unwrap();
+            let post = db
+                .insert_post(
+                    "look at this https://example.com/page.".to_string(),
+                    account.clone(),
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            let preview = db.link_preview(post.id, None).await.unwrap().unwrap();
+            assert_eq!(preview.url, "https://example.com/page");
+            assert_eq!(preview.title.as_deref(), Some("Tom & Jerry"));
+            assert_eq!(preview.description.as_deref(), Some("A cat and a mouse"));
+            assert_eq!(
+                preview.image_url.as_deref(),
+                Some("https://example.com/cover.png")
+            );
+            assert_eq!(db.link_preview(post.id, None).await.unwrap(), Some(preview));
+            assert_eq!(fetcher.fetches.load(std::sync::atomic::Ordering::SeqCst), 1);
+            let plain = db
+                .insert_post("no links".to_string(), account, None, None)
+                .await
+                .unwrap();
+            assert_eq!(db.link_preview(plain.id, None).await.unwrap(), None);
+        }
+
+        #[tokio::test]
+        async fn link_previews_refuse_private_addresses() {
+            use previews::PreviewFetcher;
+            for ip in [
+                "127.0.0.1",
+                "10.1.2.3",
+                "172.16.0.1",
+                "192.168.1.1",
+                "169.254.169.254",
+                "100.64.0.1",
+                "0.0.0.0",
+                "::1",
+                "fd00::1",
+                "fe80::1",
+                "::ffff:127.0.0.1",
+                "64:ff9b::a01:203",
+                "2002:a01:203::1",
+                "::10.1.2.3",
+                "198.18.0.1",
+                "240.0.0.1",
+            ] {
+                assert!(!previews::is_public(ip.parse().unwrap()), "{ip}");
+            }
+            assert!(previews::is_public("93.184.216.34".parse().unwrap()));
+            assert!(previews::is_public("2606:2800:220:1::1".parse().unwrap()));
+            let fetcher = previews::HttpFetcher::default();
+            assert!(fetcher.fetch("http://127.0.0.1:8080/").await.is_err());
+            assert!(fetcher.fetch("http://localhost/").await.is_err());
+            assert!(fetcher.fetch("file:///etc/passwd").await.is_err());
+        }
+
+        #[tokio::test]
+        async fn link_previews_check_every_hop_and_cap_the_body() {
+            use previews::PreviewFetcher;
+            let app = Router::new()
+                .route(
+                    "/page",
+                    get(|| async { Html("<html><head><title>Local</title></head></html>") }),
+                )
+                .route("/hop", get(|| async { Redirect::temporary("/page") }))
+                .route(
+                    "/private",
+                    get(|| async { Redirect::temporary("http://10.1.2.3/") }),
+                )
+                .route(
+                    "/big",
+                    get(|| async { Html("a".repeat(previews::MAX_BYTES * 4)) }),
+                )
+                .route(
+                    "/json",
+                    get(|| async { ([(header::CONTENT_TYPE, "application/json")], "{}") }),
+                );
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(
+                Server::from_tcp(listener)
+                    .unwrap()
+                    .serve(app.into_make_service()),
+            );
+            let fetcher = previews::HttpFetcher::allowing(|ip| ip.is_loopback());
+            let html = fetcher.fetch(&format!("http://{addr}/hop")).await.unwrap();
+            assert_eq!(previews::parse("", &html).title.as_deref(), Some("Local"));
+            assert!(matches!(
+                fetcher.fetch(&format!("http://{addr}/private")).await,
+                Err(AppError::Preview(message)) if message.contains("private address")
+            ));
+            let html = fetcher.fetch(&format!("http://{addr}/big")).await.unwrap();
+            assert_eq!(html.len(), previews::MAX_BYTES);
+            assert!(matches!(
+                fetcher.fetch(&format!("http://{addr}/json")).await,
+                Err(AppError::Preview(message)) if message.contains("isn't an html page")
+            ));
+        }
+
+        /// Two accounts that have liked, commented on, blocked and muted each other.
+        async fn accounts_with_activity(db: &Database) -> (Account, Account, Session) {
+            let leaving = db.insert_account("leaving".to_string()).await.unwrap();
+            let staying = db.insert_account("staying".to_string()).await.unwrap();
+            let session = db.insert_session(leaving.id).await.unwrap();
+            db.insert_session(leaving.id).await.unwrap();
+            let leaving_post = db
+                .insert_post("leaving".to_string(), leaving.clone(), None, None)
+                .await
+                .unwrap();
+            let staying_post = db
+                .insert_post("staying".to_string(), staying.clone(), None, None)
+                .await
+                .unwrap();
+            db.insert_like(staying.id, leaving_post.id).await.unwrap();
+            db.insert_like(leaving.id, staying_post.id).await.unwrap();
+            db.insert_comment(leaving_post.id, staying.id, "hi".to_string())
+                .await
+                .unwrap();
+            db.insert_comment(staying_post.id, leaving.id, "hey".to_string())
+                .await
+                .unwrap();
+            db.insert_report(
+                staying.id,
+                &ReportTarget::Post(leaving_post.id),
+                ReportReason::Spam,
+            )
+            .await
+            .unwrap();
+            db.set_blocked(leaving.id, staying.id, true).await.unwrap();
+            db.set_muted(staying.id, leaving.id, true).await.unwrap();
+            (leaving, staying, session)
+        }
+
+        #[tokio::test]
+        async fn exports_parse_and_leave_out_session_identifiers() {
+            let db = database().await;
+            let (leaving, _, session) = accounts_with_activity(&db).await;
+            db.save_draft(leaving.id, None, "unsent".to_string())
+                .await
+                .unwrap();
+            let mut out = vec![];
+            db.export_account(leaving.id, &mut out).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+            assert_eq!(json["account"]["name"], "leaving");
+            assert_eq!(json["posts"][0]["body"], "leaving");
+            assert_eq!(json["comments"][0]["body"], "hey");
+            assert_eq!(json["likes"].as_array().unwrap().len(), 1);
+            assert_eq!(json["drafts"][0]["body"], "unsent");
+            let sessions = json["sessions"].as_array().unwrap();
+            assert_eq!(sessions.len(), 2);
+            assert!(sessions
+                .iter()
+                .all(|session| session.get("identifier").is_none()));
+            assert!(!String::from_utf8(out)
+                .unwrap()
+                .contains(&session.identifier));
+        }
+
+        #[tokio::test]
+        async fn delete_account_removes_owned_data() {
+            let db = database().await;
+            let (leaving, staying, session) = accounts_with_activity(&db).await;
+            db.delete_account(leaving.id, DeletionMode::Remove)
+                .await
+                .unwrap();
+            assert!(db.account_by_id(leaving.id).await.is_err());
+            assert!(db.session_by_identifer(&session.identifier).await.is_err());
+            let posts = db.posts(None).await.unwrap();
+            assert_eq!(posts.len(), 1);
+            assert_eq!(posts[0].account_id, staying.id);
+            assert_eq!(posts[0].like_count, None);
+            assert_eq!(posts[0].comment_count, 0);
+            assert!(db.report_queue().await.unwrap().is_empty());
+            assert_eq!(
+                db.account_relations(staying.id).await.unwrap(),
+                Default::default()
+            );
+        }
+
+        #[tokio::test]
+        async fn delete_account_can_anonymise_posts() {
+            let db = database().await;
+            let (leaving, staying, _) = accounts_with_activity(&db).await;
+            db.delete_account(leaving.id, DeletionMode::Anonymise)
+                .await
+                .unwrap();
+            assert!(db.account_by_id(leaving.id).await.is_err());
+            let posts = db.posts(None).await.unwrap();
+            assert_eq!(posts.len(), 2);
+            let anonymised = posts.iter().find(|p| p.body == "leaving").unwrap();
+            assert_eq!(anonymised.account_name, DELETED_ACCOUNT_NAME);
+            let comments = db.comments_by_post_id(posts[0].id, None).await.unwrap();
+            assert!(comments.iter().all(|c| c.account_id != leaving.id));
+            assert!(db
+                .search("".to_string(), Some(&staying))
+                .await
+                .unwrap()
+                .iter()
+                .all(|r| r.name == "staying"));
+        }
+    }
+}
+
+pub mod models {
+    use serde::{Deserialize, Serialize};
+
+    /// Never leaves the server, send `PublicAccount` to the client instead.
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct Account {
+        pub id: i64,
+        pub name: String,
+        pub display_name: Option<String>,
+        pub is_admin: bool,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct PublicAccount {
+        pub id: i64,
+        pub name: String,
+        pub display_name: Option<String>,
+    }
+
+    impl From<Account> for PublicAccount {
+        fn from(value: Account) -> Self {
+            Self {
+                id: value.id,
+                name: value.name,
+                display_name: value.display_name,
+            }
+        }
+    }
+
+    /// The logged in account, only sent to that account's own client so
+    /// other people never learn who the admins are.
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct SessionAccount {
+        pub id: i64,
+        pub name: String,
+        pub display_name: Option<String>,
+        pub is_admin: bool,
+    }
+
+    impl From<Account> for SessionAccount {
+        fn from(value: Account) -> Self {
+            Self {
+                id: value.id,
+                name: value.name,
+                display_name: value.display_name,
+                is_admin: value.is_admin,
+            }
+        }
+    }
+
+    /// The parts of a profile its owner writes, blank ones are `None`.
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct ProfileFields {
+        pub bio: Option<String>,
+        pub website: Option<String>,
+        pub pronouns: Option<String>,
+    }
+
+    /// Name of the account anonymised posts and comments are moved to.
+    pub const DELETED_ACCOUNT_NAME: &str = "[deleted]";
+
+    #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+    pub enum DeletionMode {
+        Remove,
+        Anonymise,
+    }
+
+    impl DeletionMode {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                DeletionMode::Remove => "remove",
+                DeletionMode::Anonymise => "anonymise",
+            }
+        }
+    }
+
+    impl std::str::FromStr for DeletionMode {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "remove" => Ok(DeletionMode::Remove),
+                "anonymise" => Ok(DeletionMode::Anonymise),
+                _ => Err(format!("{s} is not a deletion mode")),
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct ExportedAccount {
+        pub id: i64,
+        pub name: String,
+        pub display_name: Option<String>,
+        pub bio: Option<String>,
+        pub website: Option<String>,
+        pub pronouns: Option<String>,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct ExportedPost {
+        pub id: i64,
+        pub body: String,
+        pub quoted_post_id: Option<i64>,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct ExportedComment {
+        pub id: i64,
+        pub post_id: i64,
+        pub body: String,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct ExportedSession {
+        pub id: i64,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    /// Accounts someone has blocked or muted.
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct AccountRelations {
+        pub blocked: Vec<PublicAccount>,
+        pub muted: Vec<PublicAccount>,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct AdminAccount {
+        pub id: i64,
+        pub name: String,
+        pub is_admin: bool,
+        pub suspended_at: Option<i64>,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct ModerationAction {
+        pub id: i64,
+        pub moderator_name: Option<String>,
+        pub action: String,
+        pub target_kind: String,
+        pub target_id: i64,
+        pub summary: String,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
+    pub struct Session {
+        pub id: i64,
+        pub identifier: String,
+        pub account_id: i64,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Default, Serialize, Deserialize, PartialEq, Debug)]
+    pub struct Post {
+        pub id: i64,
+        pub body: String,
+        pub account_id: i64,
+        pub account_name: String,
+        pub account_display_name: Option<String>,
+        pub like_count: Option<i64>,
+        pub liked_by_current_account: Option<i64>,
+        pub bookmarked_by_current_account: Option<i64>,
+        /// Where the saved list picks up on the next page.
+        pub bookmark_id: Option<i64>,
+        pub updated_at: i64,
+        pub created_at: i64,
+        pub comment_count: i64,
+        pub publish_at: Option<i64>,
+        pub attachments: Option<String>,
+        pub poll_closes_at: Option<i64>,
+        pub poll: Option<String>,
+        pub poll_vote: Option<i64>,
+        pub quoted_post_id: Option<i64>,
+        pub repost_count: i64,
+        pub quote_count: i64,
+        pub reposted_by_current_account: Option<i64>,
+        /// Whoever reposted it last, the feed shows a post at the time of its latest repost.
+        pub reposted_by: Option<String>,
+        pub quoted_body: Option<String>,
+        pub quoted_account_name: Option<String>,
+    }
+
+    impl Post {
+        /// `poll` is a json array, with vote counts once the current
+        /// account has voted or the poll has closed.
+        pub fn poll_options(&self) -> Vec<PollOption> {
+            self.poll
+                .as_deref()
+                .and_then(|poll| serde_json::from_str(poll).ok())
+                .unwrap_or_default()
+        }
+
+        /// `attachments` comes out of the database as a comma separated list.
+        pub fn attachment_names(&self) -> Vec<&str> {
+            match &self.attachments {
+                Some(names) => names.split(',').collect(),
+                None => vec![],
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct Bookmark {
+        pub id: i64,
+        pub account_id: i64,
+        pub post_id: i64,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct SavedPage {
+        pub posts: Vec<Post>,
+        pub has_more: bool,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct Repost {
+        pub id: i64,
+        pub account_id: i64,
+        pub post_id: i64,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct PollOption {
+        pub id: i64,
+        pub body: String,
+        pub votes: Option<i64>,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct NewPoll {
+        pub options: Vec<String>,
+        /// Seconds after the post is published.
+        pub closes_in: i64,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct PollVote {
+        pub id: i64,
+        pub post_id: i64,
+        pub option_id: i64,
+        pub account_id: i64,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct LinkPreview {
+        pub url: String,
+        pub title: Option<String>,
+        pub description: Option<String>,
+        pub image_url: Option<String>,
+    }
+
+    /// The first http(s) url in some text, minus any punctuation it ends a sentence with.
+    pub fn first_url(text: &str) -> Option<&str> {
+        text.split_whitespace()
+            .find(|word| word.starts_with("https://") || word.starts_with("http://"))
+            .map(|word| word.trim_end_matches(|c: char| ".,!?;:)'\"".contains(c)))
+    }
+
+    /// Only http and https urls make it into an `href` or `src`,
+    /// `javascript:` and friends would run in the reader's browser.
+    pub fn sanitize_url(url: &str) -> Option<String> {
+        let url = url.trim();
+        let lower = url.to_ascii_lowercase();
+        let rest = lower
+            .strip_prefix("https://")
+            .or_else(|| lower.strip_prefix("http://"))?;
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        let host = authority.rsplit('@').next().unwrap_or_default();
+        let host = match host.strip_prefix('[') {
+            Some(ipv6) => ipv6.split_once(']').map_or("", |(ipv6, _)| ipv6),
+            None => host.split(':').next().unwrap_or_default(),
+        };
+        let has_host =
+            !host.is_empty() && !host.contains(|c: char| c.is_whitespace() || c.is_control());
+        has_host.then(|| url.to_string())
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct Attachment {
+        pub id: i64,
+        pub account_id: i64,
+        pub post_id: Option<i64>,
+        pub filename: String,
+        pub width: i64,
+        pub height: i64,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    pub trait HasAccount {
+        fn account(&self) -> PublicAccount;
+    }
+
+    impl HasAccount for Post {
+        fn account(&self) -> PublicAccount {
+            PublicAccount {
+                name: self.account_name.clone(),
+                display_name: self.account_display_name.clone(),
+                id: self.account_id,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl PublicAccount {
+        pub fn initial(&self) -> String {
+            self.label().chars().next().unwrap().to_string()
+        }
+
+        /// The display name when there is one, the handle otherwise.
+        pub fn label(&self) -> &str {
+            self.display_name.as_deref().unwrap_or(&self.name)
+        }
+    }
+
+    impl SessionAccount {
+        /// Same as `PublicAccount::label`.
+        pub fn label(&self) -> &str {
+            self.display_name.as_deref().unwrap_or(&self.name)
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+    pub enum ReportTarget {
+        Post(i64),
+        Comment(i64),
+    }
+
+    impl ReportTarget {
+        pub fn kind(&self) -> &'static str {
+            match self {
+                ReportTarget::Post(_) => "post",
+                ReportTarget::Comment(_) => "comment",
+            }
+        }
+
+        pub fn id(&self) -> i64 {
+            match self {
+                ReportTarget::Post(id) | ReportTarget::Comment(id) => *id,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+    pub enum ReportReason {
+        Spam,
+        Harassment,
+        Hate,
+        Misinformation,
+        Other,
+    }
+
+    impl ReportReason {
+        pub const ALL: [ReportReason; 5] = [
+            ReportReason::Spam,
+            ReportReason::Harassment,
+            ReportReason::Hate,
+            ReportReason::Misinformation,
+            ReportReason::Other,
+        ];
+
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                ReportReason::Spam => "spam",
+                ReportReason::Harassment => "harassment",
+                ReportReason::Hate => "hate",
+                ReportReason::Misinformation => "misinformation",
+                ReportReason::Other => "other",
+            }
+        }
+
+        pub fn label(&self) -> &'static str {
+            match self {
+                ReportReason::Spam => "Spam",
+                ReportReason::Harassment => "Harassment or bullying",
+                ReportReason::Hate => "Hateful content",
+                ReportReason::Misinformation => "Misinformation",
+                ReportReason::Other => "Something else",
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+    pub enum ReportResolution {
+        Dismiss,
+        Action,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct ReportSummary {
+        pub target_kind: String,
+        pub target_id: i64,
+        pub account_name: String,
+        pub body: String,
+        pub reasons: String,
+        pub report_count: i64,
+        pub created_at: i64,
+    }
+
+    impl ReportSummary {
+        pub fn target(&self) -> ReportTarget {
+            match self.target_kind.as_str() {
+                "comment" => ReportTarget::Comment(self.target_id),
+                _ => ReportTarget::Post(self.target_id),
+            }
+        }
+    }
+
+    #[derive(Clone, Default, PartialEq)]
+    pub struct LoginCode {
+        pub id: i64,
+        pub login_code_hash: String,
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct PublishedPost {
+        pub id: i64,
+        pub account_id: i64,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
+    pub struct InsertPost {
+        pub id: i64,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct Draft {
+        pub id: i64,
+        pub account_id: i64,
+        pub body: String,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
+    pub struct Like {
+        pub id: i64,
+        pub account_id: i64,
+        pub post_id: i64,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+    pub struct Comment {
+        pub id: i64,
+        pub account_id: i64,
+        pub account_name: String,
+        pub post_id: i64,
+        pub body: String,
+        pub updated_at: i64,
+        pub created_at: i64,
+    }
+
+    impl HasAccount for Comment {
+        fn account(&self) -> PublicAccount {
+            PublicAccount {
+                name: self.account_name.clone(),
+                id: self.account_id,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Clone, Default, Serialize, Deserialize, PartialEq, Debug)]
+    pub struct SearchResult {
+        pub id: i64,
+        pub name: String,
+    }
+
+    impl HasAccount for SearchResult {
+        fn account(&self) -> PublicAccount {
+            PublicAccount {
+                name: self.name.clone(),
+                id: self.id,
+                ..Default::default()
+            }
+        }
+    }
+}
+
+#[Error]
+#[derive(Clone, Serialize, Deserialize)]
+pub enum AppError {
+    NotFound,
+    Utf8,
+    Http,
+    AssetExt,
+    Migrate(String),
+    MigrationMissing(i64),
+    MigrationChecksumMismatch(i64),
+    MigrationDirty(i64),
+    DatabaseInsert,
+    DatabaseSelect,
+    Database,
+    Rollback,
+    RollbackMissing(i64),
+    Env(Vec<String>),
+    DatabaseUniqueIndex,
+    Io(String),
+    InvalidImage,
+    Preview(String),
+    PollClosed,
+    NameChangeTooSoon,
+    Forbidden,
+}
+
+#[derive(Serialize, Default, Deserialize, Copy, Clone, Debug)]
+pub struct SignupName {
+    pub has_allowed_chars: SignupNameState,
+    pub symbols_between_letters: SignupNameState,
+    pub less_than_max_len: SignupNameState,
+    pub greater_than_min_len: SignupNameState,
+    pub is_not_reserved: SignupNameState,
+    pub is_available: SignupNameState,
+}
+
+/// Names that would look official or clash with a route.
+const RESERVED_NAMES: &[&str] = &[
+    "about",
+    "account",
+    "admin",
+    "administrator",
+    "api",
+    "assets",
+    "deleted",
+    "healthz",
+    "help",
+    "login",
+    "logout",
+    "metrics",
+    "mod",
+    "moderator",
+    "readyz",
+    "root",
+    "search",
+    "settings",
+    "signup",
+    "staff",
+    "support",
+    "system",
+    "uploads",
+    "yallpost",
+];
+
+/// Letters, numbers, `_` and `.`, where the symbols only go between
+/// letters or numbers and never two in a row.
+pub fn validate_name(name: &String) -> SignupName {
+    let has_allowed_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        .into();
+    let is_symbol = |c: char| c == '_' || c == '.';
+    let symbols_between_letters = (!name.starts_with(is_symbol)
+        && !name.ends_with(is_symbol)
+        && !name
+            .chars()
+            .zip(name.chars().skip(1))
+            .any(|(a, b)| is_symbol(a) && is_symbol(b)))
+    .into();
+    let greater_than_min_len = (name.chars().count() >= 3).into();
+    let less_than_max_len = (name.chars().count() <= 20).into();
+    let is_not_reserved = (!is_reserved_name(name)).into();
+    SignupName {
+        has_allowed_chars,
+        symbols_between_letters,
+        less_than_max_len,
+        greater_than_min_len,
+        is_not_reserved,
+        ..Default::default()
+    }
+}
+
+/// Also used for display names, so spaces don't get `Ad Min` past the list.
+pub fn is_reserved_name(name: &str) -> bool {
+    let skeleton = name_skeleton(&name.split_whitespace().collect::<String>());
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| name_skeleton(reserved) == skeleton)
+}
+
+/// What's left of a name once case, symbols and lookalike characters are
+/// set aside. Two accounts can't share a skeleton, so `rn.bob` can't pass
+/// for `m_bob` and `a1ice` can't pass for `alice`.
+pub fn name_skeleton(name: &str) -> String {
+    name.to_ascii_lowercase()
+        .replace(['_', '.'], "")
+        .replace("rn", "m")
+        .replace("vv", "w")
+        .chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | 'i' => 'l',
+            '5' => 's',
+            c => c,
+        })
+        .collect()
+}
+
+impl SignupName {
+    fn is_valid(&self) -> bool {
+        self.has_allowed_chars == SignupNameState::Valid
+            && self.symbols_between_letters == SignupNameState::Valid
+            && self.less_than_max_len == SignupNameState::Valid
+            && self.greater_than_min_len == SignupNameState::Valid
+            && self.is_not_reserved == SignupNameState::Valid
+    }
+}
+
+impl From<bool> for SignupNameState {
+    fn from(value: bool) -> Self {
+        match value {
+            true => SignupNameState::Valid,
+            false => SignupNameState::Invalid,
+        }
+    }
+}
+
+/// Rules for post and comment bodies, checked as you type and again in the
+/// server functions.
+pub mod validation {
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct BodyRules {
+        pub max_chars: usize,
+        pub max_lines: usize,
+    }
+
+    pub const POST_BODY: BodyRules = BodyRules {
+        max_chars: 500,
+        max_lines: 20,
+    };
+
+    pub const COMMENT_BODY: BodyRules = BodyRules {
+        max_chars: 300,
+        max_lines: 10,
+    };
+
+    pub const DISPLAY_NAME: BodyRules = BodyRules {
+        max_chars: 40,
+        max_lines: 1,
+    };
+
+    pub const BIO: BodyRules = BodyRules {
+        max_chars: 160,
+        max_lines: 4,
+    };
+
+    pub const WEBSITE: BodyRules = BodyRules {
+        max_chars: 200,
+        max_lines: 1,
+    };
+
+    pub const PRONOUNS: BodyRules = BodyRules {
+        max_chars: 20,
+        max_lines: 1,
+    };
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct BodyCheck {
+        pub chars: usize,
+        pub lines: usize,
+        pub rules: BodyRules,
+    }
+
+    /// Trims the body and drops control characters other than newlines
+    /// and tabs, along with the bidi overrides that can flip how text reads.
+    pub fn clean_body(body: &str) -> String {
+        body.chars()
+            .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+            .filter(|c| !matches!(c, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'))
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+
+    pub fn validate_body(body: &str, rules: BodyRules) -> BodyCheck {
+        let body = clean_body(body);
+        BodyCheck {
+            chars: body.chars().count(),
+            lines: body.lines().count(),
+            rules,
+        }
+    }
+
+    impl BodyCheck {
+        pub fn message(&self) -> Option<String> {
+            if self.chars == 0 {
+                Some("Write something first".to_string())
+            } else if self.chars > self.rules.max_chars {
+                Some(format!("Keep it under {} characters", self.rules.max_chars))
+            } else if self.lines > self.rules.max_lines {
+                Some(format!("Keep it under {} lines", self.rules.max_lines))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// What the client gets back when a server function can't do what was asked.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ApiError {
+    Unauthenticated,
+    Forbidden,
+    NotFound,
+    Validation(Vec<FieldError>),
+    RateLimited,
+    Conflict(String),
+    Internal,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ApiError {
+    /// Checks a post or comment body, handing back the cleaned up body
+    /// that should be stored.
+    #[cfg(backend)]
+    fn body(body: &str, rules: validation::BodyRules) -> Result<String, ApiError> {
+        match validation::validate_body(body, rules).message() {
+            Some(message) => Err(ApiError::validation("body", &message)),
+            None => Ok(validation::clean_body(body)),
+        }
+    }
+
+    /// Like `body` for fields that can be left blank, which clears them.
+    #[cfg(backend)]
+    fn optional_field(
+        value: &str,
+        field: &str,
+        rules: validation::BodyRules,
+    ) -> Result<Option<String>, ApiError> {
+        let value = validation::clean_body(value);
+        if value.is_empty() {
+            return Ok(None);
+        }
+        match validation::validate_body(&value, rules).message() {
+            Some(message) => Err(ApiError::validation(field, &message)),
+            None => Ok(Some(value)),
+        }
+    }
+
+    fn validation(field: &str, message: &str) -> Self {
+        ApiError::Validation(vec![FieldError {
+            field: field.to_string(),
+            message: message.to_string(),
+        }])
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::Unauthenticated => f.write_str("You need to be logged in to do that"),
+            ApiError::Forbidden => f.write_str("You aren't allowed to do that"),
+            ApiError::NotFound => f.write_str("That doesn't exist anymore"),
+            ApiError::Validation(errors) => f.write_str(
+                &errors
+                    .iter()
+                    .map(|e| e.message.clone())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            ApiError::RateLimited => f.write_str("Slow down! Try again in a minute"),
+            ApiError::Conflict(message) => f.write_str(message),
+            ApiError::Internal => f.write_str("Something went wrong, try again"),
+        }
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(value: AppError) -> Self {
+        match value {
+            AppError::NotFound => ApiError::NotFound,
+            AppError::Forbidden => ApiError::Forbidden,
+            AppError::DatabaseUniqueIndex => ApiError::Conflict("That already exists".to_string()),
+            AppError::InvalidImage => {
+                ApiError::validation("image", "That image couldn't be read, try a jpeg or png")
+            }
+            AppError::PollClosed => ApiError::Conflict("This poll has closed".to_string()),
+            AppError::NameChangeTooSoon => ApiError::Conflict(format!(
+                "Handles can only change once every {} days",
+                NAME_CHANGE_COOLDOWN_SECS / (24 * 60 * 60)
+            )),
+            _ => {
+                #[cfg(backend)]
+                tracing::error!(err = %value, "request failed");
+                ApiError::Internal
+            }
+        }
+    }
+}
+
+impl From<ServerFnError> for ApiError {
+    fn from(_value: ServerFnError) -> Self {
+        ApiError::Internal
+    }
+}
+
+type ServerResult<T> = Result<Result<T, ApiError>, ServerFnError>;
+
+/// Flattens a server function result so callers only deal with `ApiError`.
+fn api_result<T>(result: ServerResult<T>) -> Result<T, ApiError> {
+    result.unwrap_or_else(|err| Err(err.into()))
+}
+
+const ATTACHMENTS_PER_POST: usize = 4;
+#[cfg(backend)]
+const SAVED_PAGE_SIZE: i64 = 20;
+const NAME_CHANGE_COOLDOWN_SECS: i64 = 30 * 24 * 60 * 60;
+const HANDLE_REDIRECT_SECS: i64 = 90 * 24 * 60 * 60;
+#[cfg(backend)]
+const POSTS_PER_MINUTE: i64 = 5;
+#[cfg(backend)]
+const COMMENTS_PER_MINUTE: i64 = 10;
+#[cfg(backend)]
+const UPLOADS_PER_MINUTE: i64 = 10;
 
-#[derive(Serialize, Default, Deserialize, Copy, Clone, Debug)]
-pub struct SignupName {
-    pub is_alphanumeric: SignupNameState,
-    pub less_than_max_len: SignupNameState,
-    pub greater_than_min_len: SignupNameState,
-    pub is_available: SignupNameState,
+/// Availability only gets checked for names that pass the other rules.
+#[server(CheckName, "", "Cbor")]
+async fn check_name(
+    sx: DioxusServerContext,
+    name: String,
+) -> Result<Result<SignupName, ApiError>, ServerFnError> {
+    let mut signup_name = validate_name(&name);
+    if !signup_name.is_valid() {
+        return Ok(Ok(signup_name));
+    }
+    let db = use_db(&sx);
+    match db.name_available(&name).await {
+        Ok(available) => {
+            signup_name.is_available = available.into();
+            Ok(Ok(signup_name))
+        }
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(Signup, "", "Cbor")]
+async fn signup(
+    sx: DioxusServerContext,
+    name: String,
+) -> Result<Result<SessionAccount, ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let signup_name = validate_name(&name);
+    if signup_name.is_not_reserved == SignupNameState::Invalid {
+        return Ok(Err(ApiError::validation("name", "That name is reserved")));
+    }
+    if !signup_name.is_valid() {
+        return Ok(Err(ApiError::validation(
+            "name",
+            "Names are 3 to 20 letters, numbers, _ or . with the symbols between letters",
+        )));
+    }
+    let account = match db.insert_account(name).await {
+        Ok(a) => a,
+        Err(err) => match err {
+            AppError::DatabaseUniqueIndex => {
+                return Ok(Err(ApiError::Conflict("That name is taken".to_string())));
+            }
+            _ => return Ok(Err(err.into())),
+        },
+    };
+    let session = match db.insert_session(account.id).await {
+        Ok(session) => session,
+        Err(err) => return Ok(Err(err.into())),
+    };
+    sx.response_headers_mut().insert(
+        axum::http::header::SET_COOKIE,
+        axum::http::HeaderValue::from_str(
+            backend::set_cookie(session, use_env(&sx).cookie_secure).as_str(),
+        )
+        .unwrap(),
+    );
+    Ok(Ok(account.into()))
+}
+
+/// An empty display name clears it, the handle shows instead.
+#[server(SetDisplayName, "", "Cbor")]
+async fn set_display_name(
+    sx: DioxusServerContext,
+    display_name: String,
+) -> Result<Result<SessionAccount, ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    let display_name =
+        match ApiError::optional_field(&display_name, "display_name", validation::DISPLAY_NAME) {
+            Ok(display_name) => display_name,
+            Err(err) => return Ok(Err(err)),
+        };
+    if display_name.as_deref().map_or(false, is_reserved_name) {
+        return Ok(Err(ApiError::validation(
+            "display_name",
+            "That name is reserved",
+        )));
+    }
+    match db.set_display_name(account.id, display_name).await {
+        Ok(account) => Ok(Ok(account.into())),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(AccountProfileFields, "", "Cbor")]
+async fn account_profile_fields(
+    sx: DioxusServerContext,
+    account_id: i64,
+) -> Result<Result<ProfileFields, ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    match db.profile_fields(account_id).await {
+        Ok(fields) => Ok(Ok(fields)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(SetProfileFields, "", "Cbor")]
+async fn set_profile_fields(
+    sx: DioxusServerContext,
+    fields: ProfileFields,
+) -> Result<Result<ProfileFields, ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    let fields = match validate_profile_fields(fields) {
+        Ok(fields) => fields,
+        Err(err) => return Ok(Err(err)),
+    };
+    match db.set_profile_fields(account.id, fields).await {
+        Ok(fields) => Ok(Ok(fields)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[cfg(backend)]
+fn validate_profile_fields(fields: ProfileFields) -> Result<ProfileFields, ApiError> {
+    let bio = ApiError::optional_field(&fields.bio.unwrap_or_default(), "bio", validation::BIO)?;
+    let website = ApiError::optional_field(
+        &fields.website.unwrap_or_default(),
+        "website",
+        validation::WEBSITE,
+    )?;
+    let website = match website {
+        Some(website) => match models::sanitize_url(&website) {
+            Some(website) if !website.contains(char::is_whitespace) => Some(website),
+            _ => {
+                return Err(ApiError::validation(
+                    "website",
+                    "Links start with http:// or https://",
+                ))
+            }
+        },
+        None => None,
+    };
+    let pronouns = ApiError::optional_field(
+        &fields.pronouns.unwrap_or_default(),
+        "pronouns",
+        validation::PRONOUNS,
+    )?;
+    Ok(ProfileFields {
+        bio,
+        website,
+        pronouns,
+    })
+}
+
+#[server(ChangeName, "", "Cbor")]
+async fn change_name(
+    sx: DioxusServerContext,
+    name: String,
+) -> Result<Result<SessionAccount, ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    if account.name == name {
+        return Ok(Err(ApiError::validation(
+            "name",
+            "That's already your handle",
+        )));
+    }
+    let signup_name = validate_name(&name);
+    if signup_name.is_not_reserved == SignupNameState::Invalid {
+        return Ok(Err(ApiError::validation("name", "That name is reserved")));
+    }
+    if !signup_name.is_valid() {
+        return Ok(Err(ApiError::validation(
+            "name",
+            "Names are 3 to 20 letters, numbers, _ or . with the symbols between letters",
+        )));
+    }
+    match db.change_name(account.id, name).await {
+        Ok(account) => Ok(Ok(account.into())),
+        Err(AppError::DatabaseUniqueIndex) => {
+            Ok(Err(ApiError::Conflict("That name is taken".to_string())))
+        }
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(LikePost, "", "Cbor")]
+async fn like_post(
+    sx: DioxusServerContext,
+    post_id: i64,
+) -> Result<Result<models::Like, ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    match db.insert_like(account.id, post_id).await {
+        Ok(like) => Ok(Ok(like)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(DislikePost, "", "Cbor")]
+async fn dislike_post(
+    sx: DioxusServerContext,
+    post_id: i64,
+) -> Result<Result<bool, ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    match db.delete_like(account.id, post_id).await {
+        Ok(result) => Ok(Ok(result)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(CommentsByPostId, "", "Cbor")]
+async fn comments_by_post_id(
+    sx: DioxusServerContext,
+    post_id: i64,
+) -> Result<Result<Vec<Comment>, ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let account = get_account(&sx).await;
+    match db.comments_by_post_id(post_id, account.as_ref()).await {
+        Ok(comments) => Ok(Ok(comments)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(Search, "", "Cbor")]
+async fn search(
+    sx: DioxusServerContext,
+    query: String,
+) -> Result<Result<Vec<SearchResult>, ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let account = get_account(&sx).await;
+    match db.search(query, account.as_ref()).await {
+        Ok(results) => Ok(Ok(results)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+impl From<AppError> for ServerFnError {
+    fn from(_value: AppError) -> Self {
+        ServerFnError::ServerError("Internal server error".to_string())
+    }
+}
+
+#[server(Login, "", "Cbor")]
+async fn login(
+    sx: DioxusServerContext,
+    login_code: String,
+) -> Result<Result<(SessionAccount, Vec<Post>), ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let account = match db.account_by_login_code(login_code).await {
+        Ok(account) => account,
+        Err(err) => return Ok(Err(err.into())),
+    };
+    match db
+        .restore_account(account.id, use_env(&sx).deletion_grace)
+        .await
+    {
+        Ok(true) => tracing::info!(account_id = account.id, "account restored"),
+        Ok(false) => {}
+        Err(err) => return Ok(Err(err.into())),
+    }
+    let session = match db.insert_session(account.id).await {
+        Ok(session) => session,
+        Err(err) => return Ok(Err(err.into())),
+    };
+    sx.response_headers_mut().insert(
+        axum::http::header::SET_COOKIE,
+        axum::http::HeaderValue::from_str(
+            backend::set_cookie(session, use_env(&sx).cookie_secure).as_str(),
+        )
+        .unwrap(),
+    );
+    match db.posts(Some(&account)).await {
+        Ok(posts) => Ok(Ok((account.into(), posts))),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[cfg(backend)]
+fn use_db(sx: &DioxusServerContext) -> backend::Database {
+    sx.get::<backend::Database>().unwrap()
+}
+
+#[cfg(backend)]
+fn use_env(sx: &DioxusServerContext) -> backend::Env {
+    sx.get::<backend::Env>().unwrap()
+}
+
+#[cfg(backend)]
+fn use_session(sx: &DioxusServerContext) -> Option<Session> {
+    if let Some(session) = sx.get::<Option<Session>>() {
+        session
+    } else {
+        None
+    }
+}
+
+#[cfg(backend)]
+async fn get_account(sx: &DioxusServerContext) -> Option<models::Account> {
+    let db = use_db(sx);
+    if let Some(Some(session)) = sx.get::<Option<Session>>() {
+        db.account_by_id(session.account_id).await.ok()
+    } else {
+        None
+    }
+}
+
+/// Logging out twice, or with a session that's already gone, still logs out.
+#[server(Logout, "", "Cbor")]
+async fn logout(sx: DioxusServerContext) -> Result<Result<(), ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let Some(session) = use_session(&sx) else { return Ok(Ok(())) };
+    match db.delete_session_by_identifier(&session.identifier).await {
+        Ok(_) | Err(AppError::NotFound) => Ok(Ok(())),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+/// The only way a login code reaches the client, it's shown once
+/// and the previous code stops working.
+#[server(RotateLoginCode, "", "Cbor")]
+async fn rotate_login_code(
+    sx: DioxusServerContext,
+) -> Result<Result<String, ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    match db.rotate_login_code(account.id).await {
+        Ok(login_code) => Ok(Ok(login_code)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(DeleteAccount, "", "Cbor")]
+async fn delete_account(
+    sc: DioxusServerContext,
+    mode: DeletionMode,
+) -> Result<Result<(), ApiError>, ServerFnError> {
+    let db = use_db(&sc);
+    let Some(session) = use_session(&sc) else { return Ok(Err(ApiError::Unauthenticated)) };
+    match db.request_account_deletion(session.account_id, mode).await {
+        Ok(_) => Ok(Ok(())),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(AddPost, "", "Cbor")]
+async fn add_post(
+    sc: DioxusServerContext,
+    body: String,
+    draft_id: Option<i64>,
+    publish_at: Option<i64>,
+    attachment_ids: Vec<i64>,
+    poll: Option<NewPoll>,
+    quoted_post_id: Option<i64>,
+) -> Result<Result<Post, ApiError>, ServerFnError> {
+    let db = use_db(&sc);
+    let Some(account) = get_account(&sc).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    let body = match ApiError::body(&body, validation::POST_BODY) {
+        Ok(body) => body,
+        Err(err) => return Ok(Err(err)),
+    };
+    if let Err(err) = validate_publish_at(publish_at) {
+        return Ok(Err(err));
+    }
+    if let Some(Err(err)) = poll.as_ref().map(validate_poll) {
+        return Ok(Err(err));
+    }
+    if let Some(quoted_post_id) = quoted_post_id {
+        let quoted = match db.post_by_id(quoted_post_id, Some(account.clone())).await {
+            Ok(quoted) => quoted,
+            Err(err) => return Ok(Err(err.into())),
+        };
+        // the author can see their own scheduled posts, quoting one would
+        // publish its body early
+        if quoted.publish_at.is_some() {
+            return Ok(Err(ApiError::validation(
+                "quoted_post_id",
+                "That post hasn't been published yet",
+            )));
+        }
+    }
+    match db
+        .post_count_since(account.id, backend::Database::now() - 60.0)
+        .await
+    {
+        Ok(count) if count >= POSTS_PER_MINUTE => return Ok(Err(ApiError::RateLimited)),
+        Ok(_) => {}
+        Err(err) => return Ok(Err(err.into())),
+    }
+    let account_id = account.id;
+    let post = match db
+        .insert_post_with(
+            body,
+            account,
+            publish_at,
+            quoted_post_id,
+            poll.as_ref(),
+            &attachment_ids,
+        )
+        .await
+    {
+        Ok(post) => post,
+        Err(err) => return Ok(Err(err.into())),
+    };
+    if let Some(draft_id) = draft_id {
+        if let Err(err) = db.delete_draft(account_id, draft_id).await {
+            tracing::warn!(%err, draft_id, "published draft was not deleted");
+        }
+    }
+    Ok(Ok(post))
+}
+
+#[server(VotePoll, "", "Cbor")]
+async fn vote_poll(
+    sc: DioxusServerContext,
+    post_id: i64,
+    option_id: i64,
+) -> Result<Result<Post, ApiError>, ServerFnError> {
+    let db = use_db(&sc);
+    let Some(account) = get_account(&sc).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    match db.vote(account.id, post_id, option_id).await {
+        Ok(()) => {}
+        Err(AppError::DatabaseUniqueIndex) => {
+            return Ok(Err(ApiError::Conflict("You already voted".to_string())))
+        }
+        Err(err) => return Ok(Err(err.into())),
+    }
+    match db.post_by_id(post_id, Some(account)).await {
+        Ok(post) => Ok(Ok(post)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(PostLinkPreview, "", "Cbor")]
+async fn post_link_preview(
+    sc: DioxusServerContext,
+    post_id: i64,
+) -> Result<Result<Option<LinkPreview>, ApiError>, ServerFnError> {
+    let db = use_db(&sc);
+    let account = get_account(&sc).await;
+    match db.link_preview(post_id, account.as_ref()).await {
+        Ok(preview) => Ok(Ok(preview)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(UploadImage, "", "Cbor")]
+async fn upload_image(
+    sc: DioxusServerContext,
+    data: Vec<u8>,
+) -> Result<Result<Attachment, ApiError>, ServerFnError> {
+    let db = use_db(&sc);
+    let Some(account) = get_account(&sc).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    if data.len() > backend::uploads::MAX_BYTES {
+        return Ok(Err(ApiError::validation(
+            "image",
+            "Images need to be smaller than 5 MB",
+        )));
+    }
+    match db
+        .upload_count_since(account.id, backend::Database::now() - 60.0)
+        .await
+    {
+        Ok(count) if count >= UPLOADS_PER_MINUTE => return Ok(Err(ApiError::RateLimited)),
+        Ok(_) => {}
+        Err(err) => return Ok(Err(err.into())),
+    }
+    match db.insert_attachment(account.id, data).await {
+        Ok(attachment) => Ok(Ok(attachment)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[cfg(backend)]
+fn validate_poll(poll: &NewPoll) -> Result<(), ApiError> {
+    if !(2..=4).contains(&poll.options.len()) {
+        return Err(ApiError::validation("poll", "Polls need 2 to 4 options"));
+    }
+    if poll
+        .options
+        .iter()
+        .any(|option| option.trim().is_empty() || option.trim().chars().count() > 80)
+    {
+        return Err(ApiError::validation(
+            "poll",
+            "Poll options need to be 1 to 80 characters",
+        ));
+    }
+    if !(5 * 60..=7 * 24 * 60 * 60).contains(&poll.closes_in) {
+        return Err(ApiError::validation(
+            "poll",
+            "Polls can stay open from 5 minutes to 7 days",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(backend)]
+fn validate_publish_at(publish_at: Option<i64>) -> Result<(), ApiError> {
+    match publish_at {
+        Some(publish_at) if (publish_at as f64) <= backend::Database::now() => Err(
+            ApiError::validation("publish_at", "Pick a time in the future"),
+        ),
+        _ => Ok(()),
+    }
+}
+
+#[server(ScheduledPosts, "", "Cbor")]
+async fn scheduled_posts(
+    sc: DioxusServerContext,
+) -> Result<Result<Vec<Post>, ApiError>, ServerFnError> {
+    let db = use_db(&sc);
+    let Some(account) = get_account(&sc).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    match db.scheduled_posts(&account).await {
+        Ok(posts) => Ok(Ok(posts)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(UpdateScheduledPost, "", "Cbor")]
+async fn update_scheduled_post(
+    sc: DioxusServerContext,
+    post_id: i64,
+    body: String,
+    publish_at: Option<i64>,
+) -> Result<Result<Post, ApiError>, ServerFnError> {
+    let db = use_db(&sc);
+    let Some(account) = get_account(&sc).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    let body = match ApiError::body(&body, validation::POST_BODY) {
+        Ok(body) => body,
+        Err(err) => return Ok(Err(err)),
+    };
+    if let Err(err) = validate_publish_at(publish_at) {
+        return Ok(Err(err));
+    }
+    match db
+        .update_scheduled_post(account, post_id, body, publish_at)
+        .await
+    {
+        Ok(post) => Ok(Ok(post)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(CancelScheduledPost, "", "Cbor")]
+async fn cancel_scheduled_post(
+    sc: DioxusServerContext,
+    post_id: i64,
+) -> Result<Result<(), ApiError>, ServerFnError> {
+    let db = use_db(&sc);
+    let Some(account) = get_account(&sc).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    match db.cancel_scheduled_post(account.id, post_id).await {
+        Ok(_) => Ok(Ok(())),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(SaveDraft, "", "Cbor")]
+async fn save_draft(
+    sc: DioxusServerContext,
+    draft_id: Option<i64>,
+    body: String,
+) -> Result<Result<Draft, ApiError>, ServerFnError> {
+    let db = use_db(&sc);
+    let Some(account) = get_account(&sc).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    let body = match ApiError::body(&body, validation::POST_BODY) {
+        Ok(body) => body,
+        Err(err) => return Ok(Err(err)),
+    };
+    match db.save_draft(account.id, draft_id, body).await {
+        Ok(draft) => Ok(Ok(draft)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(ListDrafts, "", "Cbor")]
+async fn list_drafts(
+    sc: DioxusServerContext,
+) -> Result<Result<Vec<Draft>, ApiError>, ServerFnError> {
+    let db = use_db(&sc);
+    let Some(account) = get_account(&sc).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    match db.drafts(account.id).await {
+        Ok(drafts) => Ok(Ok(drafts)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(DeleteDraft, "", "Cbor")]
+async fn delete_draft(
+    sc: DioxusServerContext,
+    draft_id: i64,
+) -> Result<Result<(), ApiError>, ServerFnError> {
+    let db = use_db(&sc);
+    let Some(account) = get_account(&sc).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    match db.delete_draft(account.id, draft_id).await {
+        Ok(_) => Ok(Ok(())),
+        Err(err) => Ok(Err(err.into())),
+    }
 }
 
-pub fn validate_name(name: &String) -> SignupName {
-    let is_alphanumeric = name.chars().all(|c| c.is_ascii_alphanumeric()).into();
-    let greater_than_min_len = (name.len() >= 3).into();
-    let less_than_max_len = (name.len() <= 20).into();
-    SignupName {
-        is_alphanumeric,
-        less_than_max_len,
-        greater_than_min_len,
-        ..Default::default()
+#[server(LeaveComment, "", "Cbor")]
+async fn leave_comment(
+    sc: DioxusServerContext,
+    post_id: i64,
+    body: String,
+) -> Result<Result<Comment, ApiError>, ServerFnError> {
+    let db = use_db(&sc);
+    let Some(account) = get_account(&sc).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    let body = match ApiError::body(&body, validation::COMMENT_BODY) {
+        Ok(body) => body,
+        Err(err) => return Ok(Err(err)),
+    };
+    match db
+        .comment_count_since(account.id, backend::Database::now() - 60.0)
+        .await
+    {
+        Ok(count) if count >= COMMENTS_PER_MINUTE => return Ok(Err(ApiError::RateLimited)),
+        Ok(_) => {}
+        Err(err) => return Ok(Err(err.into())),
+    }
+    match db.insert_comment(post_id, account.id, body).await {
+        Ok(comment) => Ok(Ok(comment)),
+        Err(err) => Ok(Err(err.into())),
     }
 }
 
-impl SignupName {
-    fn is_valid(&self) -> bool {
-        self.is_alphanumeric == SignupNameState::Valid
-            && self.less_than_max_len == SignupNameState::Valid
-            && self.greater_than_min_len == SignupNameState::Valid
+#[cfg(backend)]
+async fn get_admin(sx: &DioxusServerContext) -> Result<models::Account, ApiError> {
+    match get_account(sx).await {
+        Some(account) if account.is_admin => Ok(account),
+        Some(_) => Err(ApiError::Forbidden),
+        None => Err(ApiError::Unauthenticated),
     }
 }
 
-impl From<bool> for SignupNameState {
-    fn from(value: bool) -> Self {
-        match value {
-            true => SignupNameState::Valid,
-            false => SignupNameState::Invalid,
-        }
+#[server(AdminPosts, "", "Cbor")]
+async fn admin_posts(
+    sx: DioxusServerContext,
+) -> Result<Result<Vec<Post>, ApiError>, ServerFnError> {
+    let admin = match get_admin(&sx).await {
+        Ok(admin) => admin,
+        Err(err) => return Ok(Err(err)),
+    };
+    let db = use_db(&sx);
+    match db.posts(Some(&admin)).await {
+        Ok(posts) => Ok(Ok(posts)),
+        Err(err) => Ok(Err(err.into())),
     }
 }
 
-#[server(Signup, "", "Cbor")]
-async fn signup(
+#[server(AdminComments, "", "Cbor")]
+async fn admin_comments(
     sx: DioxusServerContext,
-    name: String,
-) -> Result<Result<Account, SignupName>, ServerFnError> {
+) -> Result<Result<Vec<Comment>, ApiError>, ServerFnError> {
+    if let Err(err) = get_admin(&sx).await {
+        return Ok(Err(err));
+    }
     let db = use_db(&sx);
-    let mut signup_name = validate_name(&name);
-    if !signup_name.is_valid() {
-        return Ok(Err(signup_name));
+    match db.recent_comments().await {
+        Ok(comments) => Ok(Ok(comments)),
+        Err(err) => Ok(Err(err.into())),
     }
-    let account = match db.insert_account(name).await {
-        Ok(a) => a,
-        Err(err) => match err {
-            AppError::DatabaseUniqueIndex => {
-                signup_name.is_available = SignupNameState::Invalid;
-                return Ok(Err(signup_name));
-            }
-            _ => return Err(ServerFnError::Request("".to_string())),
-        },
-    };
-    let session = db.insert_session(account.id).await?;
-    sx.response_headers_mut().insert(
-        axum::http::header::SET_COOKIE,
-        axum::http::HeaderValue::from_str(backend::set_cookie(session).as_str()).unwrap(),
-    );
-    Ok(Ok(account))
 }
 
-#[server(LikePost, "", "Cbor")]
-async fn like_post(
+#[server(AdminAccounts, "", "Cbor")]
+async fn admin_accounts(
     sx: DioxusServerContext,
-    post_id: i64,
-) -> Result<Option<models::Like>, ServerFnError> {
+) -> Result<Result<Vec<AdminAccount>, ApiError>, ServerFnError> {
+    if let Err(err) = get_admin(&sx).await {
+        return Ok(Err(err));
+    }
     let db = use_db(&sx);
-    if let Some(account) = get_account(&sx).await {
-        if let Ok(like) = db.insert_like(account.id, post_id).await {
-            Ok(Some(like))
-        } else {
-            Ok(None)
-        }
-    } else {
-        Ok(None)
+    match db.recent_accounts().await {
+        Ok(accounts) => Ok(Ok(accounts)),
+        Err(err) => Ok(Err(err.into())),
     }
 }
 
-#[server(DislikePost, "", "Cbor")]
-async fn dislike_post(sx: DioxusServerContext, post_id: i64) -> Result<bool, ServerFnError> {
+#[server(ModerationLog, "", "Cbor")]
+async fn moderation_log(
+    sx: DioxusServerContext,
+) -> Result<Result<Vec<ModerationAction>, ApiError>, ServerFnError> {
+    if let Err(err) = get_admin(&sx).await {
+        return Ok(Err(err));
+    }
     let db = use_db(&sx);
-    let Some(account) = get_account(&sx).await else { return Ok(false); };
-    let result = db.delete_like(account.id, post_id).await?;
-    Ok(result)
+    match db.moderation_actions().await {
+        Ok(actions) => Ok(Ok(actions)),
+        Err(err) => Ok(Err(err.into())),
+    }
 }
 
-#[server(CommentsByPostId, "", "Cbor")]
-async fn comments_by_post_id(
+#[server(AdminDeletePost, "", "Cbor")]
+async fn admin_delete_post(
     sx: DioxusServerContext,
     post_id: i64,
-) -> Result<Vec<Comment>, ServerFnError> {
+) -> Result<Result<(), ApiError>, ServerFnError> {
+    let admin = match get_admin(&sx).await {
+        Ok(admin) => admin,
+        Err(err) => return Ok(Err(err)),
+    };
     let db = use_db(&sx);
-    let comments = db.comments_by_post_id(post_id).await?;
-    Ok(comments)
+    match db.moderate_delete_post(admin.id, post_id).await {
+        Ok(_) => Ok(Ok(())),
+        Err(err) => Ok(Err(err.into())),
+    }
 }
 
-#[server(Search, "", "Cbor")]
-async fn search(
+#[server(AdminDeleteComment, "", "Cbor")]
+async fn admin_delete_comment(
     sx: DioxusServerContext,
-    query: String,
-) -> Result<Vec<SearchResult>, ServerFnError> {
+    comment_id: i64,
+) -> Result<Result<(), ApiError>, ServerFnError> {
+    let admin = match get_admin(&sx).await {
+        Ok(admin) => admin,
+        Err(err) => return Ok(Err(err)),
+    };
     let db = use_db(&sx);
-    let results = db.search(query).await?;
-    Ok(results)
+    match db.moderate_delete_comment(admin.id, comment_id).await {
+        Ok(_) => Ok(Ok(())),
+        Err(err) => Ok(Err(err.into())),
+    }
 }
 
-impl From<AppError> for ServerFnError {
-    fn from(_value: AppError) -> Self {
-        ServerFnError::ServerError("Internal server error".to_string())
+#[server(SuspendAccount, "", "Cbor")]
+async fn suspend_account(
+    sx: DioxusServerContext,
+    account_id: i64,
+    suspend: bool,
+) -> Result<Result<(), ApiError>, ServerFnError> {
+    let admin = match get_admin(&sx).await {
+        Ok(admin) => admin,
+        Err(err) => return Ok(Err(err)),
+    };
+    let db = use_db(&sx);
+    match db
+        .moderate_suspend_account(admin.id, account_id, suspend)
+        .await
+    {
+        Ok(_) => Ok(Ok(())),
+        Err(err) => Ok(Err(err.into())),
     }
 }
 
-#[server(Login, "", "Cbor")]
-async fn login(
+#[server(Relationships, "", "Cbor")]
+async fn relationships(
     sx: DioxusServerContext,
-    login_code: String,
-) -> Result<Option<(Account, Vec<Post>)>, ServerFnError> {
+) -> Result<Result<AccountRelations, ApiError>, ServerFnError> {
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
     let db = use_db(&sx);
-    if let Some(account) = db.account_by_login_code(login_code).await.ok() {
-        let session = db.insert_session(account.id).await?;
-        sx.response_headers_mut().insert(
-            axum::http::header::SET_COOKIE,
-            axum::http::HeaderValue::from_str(backend::set_cookie(session).as_str()).unwrap(),
-        );
-        let posts = db.posts(Some(&account)).await?;
-        Ok(Some((account, posts)))
-    } else {
-        Ok(None)
+    match db.account_relations(account.id).await {
+        Ok(relations) => Ok(Ok(relations)),
+        Err(err) => Ok(Err(err.into())),
     }
 }
 
-#[cfg(backend)]
-fn use_db(sx: &DioxusServerContext) -> backend::Database {
-    sx.get::<backend::Database>().unwrap()
+#[server(SetBlocked, "", "Cbor")]
+async fn set_blocked(
+    sx: DioxusServerContext,
+    account_id: i64,
+    blocked: bool,
+) -> Result<Result<(), ApiError>, ServerFnError> {
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    if account.id == account_id {
+        return Ok(Err(ApiError::validation(
+            "account_id",
+            "You can't block yourself",
+        )));
+    }
+    let db = use_db(&sx);
+    match db.set_blocked(account.id, account_id, blocked).await {
+        Ok(_) => Ok(Ok(())),
+        Err(err) => Ok(Err(err.into())),
+    }
 }
 
-#[cfg(backend)]
-fn use_session(sx: &DioxusServerContext) -> Option<Session> {
-    if let Some(session) = sx.get::<Option<Session>>() {
-        session
-    } else {
-        None
+#[server(SetBookmarked, "", "Cbor")]
+async fn set_bookmarked(
+    sx: DioxusServerContext,
+    post_id: i64,
+    bookmarked: bool,
+) -> Result<Result<(), ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    match db.set_bookmarked(account.id, post_id, bookmarked).await {
+        Ok(_) => Ok(Ok(())),
+        Err(err) => Ok(Err(err.into())),
     }
 }
 
-#[cfg(backend)]
-async fn get_account(sx: &DioxusServerContext) -> Option<Account> {
-    let db = use_db(sx);
-    if let Some(Some(session)) = sx.get::<Option<Session>>() {
-        db.account_by_id(session.account_id).await.ok()
-    } else {
-        None
+#[server(SavedPosts, "", "Cbor")]
+async fn saved_posts(
+    sx: DioxusServerContext,
+    before: Option<i64>,
+) -> Result<Result<SavedPage, ApiError>, ServerFnError> {
+    let db = use_db(&sx);
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    match db.bookmarked_posts(&account, before).await {
+        Ok(page) => Ok(Ok(page)),
+        Err(err) => Ok(Err(err.into())),
     }
 }
 
-#[server(Logout, "", "Cbor")]
-async fn logout(sx: DioxusServerContext) -> Result<(), ServerFnError> {
+#[server(SetReposted, "", "Cbor")]
+async fn set_reposted(
+    sx: DioxusServerContext,
+    post_id: i64,
+    reposted: bool,
+) -> Result<Result<Post, ApiError>, ServerFnError> {
     let db = use_db(&sx);
-    if let Some(session) = use_session(&sx) {
-        let _ = db.delete_session_by_identifier(&session.identifier).await?;
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    if let Err(err) = db.set_reposted(account.id, post_id, reposted).await {
+        return Ok(Err(err.into()));
+    }
+    match db.post_by_id(post_id, Some(account)).await {
+        Ok(post) => Ok(Ok(post)),
+        Err(err) => Ok(Err(err.into())),
     }
-    Ok(())
 }
 
-#[server(DeleteAccount, "", "Cbor")]
-async fn delete_account(sc: DioxusServerContext) -> Result<(), ServerFnError> {
-    let db = use_db(&sc);
-    if let Some(session) = use_session(&sc) {
-        let _ = db.delete_session_by_identifier(&session.identifier).await;
-        let _ = db.delete_account_by_id(session.account_id).await;
+#[server(SetMuted, "", "Cbor")]
+async fn set_muted(
+    sx: DioxusServerContext,
+    account_id: i64,
+    muted: bool,
+) -> Result<Result<(), ApiError>, ServerFnError> {
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    if account.id == account_id {
+        return Ok(Err(ApiError::validation(
+            "account_id",
+            "You can't mute yourself",
+        )));
+    }
+    let db = use_db(&sx);
+    match db.set_muted(account.id, account_id, muted).await {
+        Ok(_) => Ok(Ok(())),
+        Err(err) => Ok(Err(err.into())),
     }
-    Ok(())
 }
 
-#[server(AddPost, "", "Cbor")]
-async fn add_post(sc: DioxusServerContext, body: String) -> Result<Option<Post>, ServerFnError> {
-    let db = use_db(&sc);
-    match get_account(&sc).await {
-        Some(account) => {
-            let post = db.insert_post(body, account).await?;
-            Ok(Some(post))
-        }
-        _ => Ok(None),
+#[server(Report, "", "Cbor")]
+async fn report(
+    sx: DioxusServerContext,
+    target: ReportTarget,
+    reason: ReportReason,
+) -> Result<Result<(), ApiError>, ServerFnError> {
+    let Some(account) = get_account(&sx).await else { return Ok(Err(ApiError::Unauthenticated)) };
+    let db = use_db(&sx);
+    match db.insert_report(account.id, &target, reason).await {
+        Ok(_) => Ok(Ok(())),
+        Err(AppError::DatabaseUniqueIndex) => Ok(Err(ApiError::Conflict(
+            "You already reported that".to_string(),
+        ))),
+        Err(err) => Ok(Err(err.into())),
     }
 }
 
-#[server(LeaveComment, "", "Cbor")]
-async fn leave_comment(
-    sc: DioxusServerContext,
-    post_id: i64,
-    body: String,
-) -> Result<Option<Comment>, ServerFnError> {
-    let db = use_db(&sc);
-    let Some(account) = get_account(&sc).await else { return Ok(None) };
-    let comment = db.insert_comment(post_id, account.id, body).await?;
-    Ok(Some(comment))
+#[server(ReportQueue, "", "Cbor")]
+async fn report_queue(
+    sx: DioxusServerContext,
+) -> Result<Result<Vec<ReportSummary>, ApiError>, ServerFnError> {
+    if let Err(err) = get_admin(&sx).await {
+        return Ok(Err(err));
+    }
+    let db = use_db(&sx);
+    match db.report_queue().await {
+        Ok(reports) => Ok(Ok(reports)),
+        Err(err) => Ok(Err(err.into())),
+    }
+}
+
+#[server(ResolveReports, "", "Cbor")]
+async fn resolve_reports(
+    sx: DioxusServerContext,
+    target: ReportTarget,
+    resolution: ReportResolution,
+) -> Result<Result<(), ApiError>, ServerFnError> {
+    let admin = match get_admin(&sx).await {
+        Ok(admin) => admin,
+        Err(err) => return Ok(Err(err)),
+    };
+    let db = use_db(&sx);
+    match db.resolve_reports(admin.id, &target, resolution).await {
+        Ok(_) => Ok(Ok(())),
+        Err(err) => Ok(Err(err.into())),
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
@@ -954,7 +6763,11 @@ enum View {
     Messages,
     Add,
     Comments(Post),
-    Profile(Account),
+    Profile(PublicAccount),
+    Admin,
+    Report(ReportTarget),
+    Quote(Post),
+    Saved,
 }
 
 #[inline_props]
@@ -979,6 +6792,7 @@ fn Nav(cx: Scope) -> Element {
     let set_view = use_set(cx, VIEW);
     let set_frame_view = use_set(cx, FRAME_VIEW);
     let logged_in = account.is_some();
+    let is_admin = matches!(account, Some(SessionAccount { is_admin: true, .. }));
     cx.render(rsx! {
         div { class: "bg-gray-900 text-white fixed lg:top-0 lg:bottom-auto bottom-0 w-full py-4 z-30 standalone:pb-8",
             div { class: "flex lg:justify-center lg:gap-4 justify-around",
@@ -1018,6 +6832,11 @@ fn Nav(cx: Scope) -> Element {
                     icon: Icons::PersonCircle,
                     text: "Profile"
                 }
+                if is_admin {
+                    rsx! {
+                        NavButton { onclick: move |_| set_view(View::Admin), icon: Icons::Shield, text: "Admin" }
+                    }
+                }
             }
         }
     })
@@ -1026,9 +6845,12 @@ fn Nav(cx: Scope) -> Element {
 #[derive(Props, Clone, Default, PartialEq, Serialize, Deserialize)]
 struct ServerProps {
     #[props(!optional)]
-    account: Option<Account>,
+    account: Option<SessionAccount>,
     posts: Vec<Post>,
     view: View,
+    /// Filled in for `/profile/:name` so the page renders with them.
+    #[props(!optional)]
+    profile_fields: Option<ProfileFields>,
 }
 
 #[allow(unreachable_code)]
@@ -1056,11 +6878,14 @@ enum Frame {
 }
 
 static READY: Atom<bool> = |_| false;
-static ACCOUNT: Atom<Option<Account>> = |_| None;
+static ACCOUNT: Atom<Option<SessionAccount>> = |_| None;
+static LOGIN_CODE: Atom<Option<String>> = |_| None;
 static VIEW: Atom<View> = |_| Default::default();
 static FRAME_VIEW: Atom<Frame> = |_| Frame::Empty;
 static POSTS: Atom<Vec<Post>> = |_| Default::default();
 static COMMENTS: Atom<Vec<Comment>> = |_| Default::default();
+static TOAST: Atom<Option<ApiError>> = |_| None;
+static RELATIONS: Atom<Option<AccountRelations>> = |_| None;
 
 fn Router(cx: Scope<ServerProps>) -> Element {
     use_init_atom_root(cx);
@@ -1071,6 +6896,7 @@ fn Router(cx: Scope<ServerProps>) -> Element {
     use_shared_state_provider(cx, || props.view.clone());
     use_shared_state_provider(cx, || props.account.clone());
     use_shared_state_provider(cx, || props.posts.clone());
+    use_shared_state_provider(cx, || props.profile_fields.clone());
     let account_state = use_atom_state(cx, ACCOUNT);
     let view_state = use_atom_state(cx, VIEW);
     let posts_state = use_atom_state(cx, POSTS);
@@ -1114,7 +6940,11 @@ fn ComponentFromView(cx: Scope, view: View) -> Element {
             View::Messages => rsx! { MessagesComponent {} },
             View::Add => rsx! { NewPost {} },
             View::Profile(account) => rsx! { Profile { account: account } },
-            View::Comments(post) => rsx! { Comments { post: post } }
+            View::Comments(post) => rsx! { Comments { post: post } },
+            View::Admin => rsx! { Admin {} },
+            View::Report(target) => rsx! { ReportComponent { target: target } },
+            View::Quote(post) => rsx! { NewPost { quoted: post } },
+            View::Saved => rsx! { SavedPostList {} }
         }
     })
 }
@@ -1140,12 +6970,14 @@ fn Root(cx: Scope) -> Element {
             Nav {}
             ComponentFromView { view: view }
             frame,
+            Toast {}
         }
     )
 }
 
 fn SearchComponent(cx: Scope) -> Element {
     let set_view = use_set(cx, VIEW);
+    let set_toast = use_set(cx, TOAST);
     let query = use_state(cx, || String::default());
     let results: &UseState<Vec<SearchResult>> = use_state(cx, || vec![]);
     let oninput = move |e: FormEvent| {
@@ -1156,13 +6988,14 @@ fn SearchComponent(cx: Scope) -> Element {
         let prev = e.value.clone();
         query.set(e.value.clone());
         cx.spawn({
-            to_owned![query, results];
+            to_owned![query, results, set_toast];
             async move {
                 TimeoutFuture::new(300).await;
                 let current = query.current().to_string();
                 if prev == current {
-                    if let Ok(r) = search(sc, current).await {
-                        results.set(r);
+                    match api_result(search(sc, current).await) {
+                        Ok(r) => results.set(r),
+                        Err(err) => set_toast(Some(err)),
                     }
                 }
             }
@@ -1170,7 +7003,7 @@ fn SearchComponent(cx: Scope) -> Element {
     };
     let search_results = results.iter().map(|sr| {
         rsx! {
-            button { onclick: move |_| set_view(View::Profile(Account { name: sr.name.clone(), ..Default::default() })), "{sr.name}" }
+            button { onclick: move |_| set_view(View::Profile(sr.account())), "{sr.name}" }
         }
     });
     render!(
@@ -1187,8 +7020,171 @@ fn MessagesComponent(cx: Scope) -> Element {
 }
 
 #[inline_props]
-fn Profile<'a>(cx: Scope, account: &'a Account) -> Element {
-    cx.render(rsx! { h1 { class: "text-2xl text-center p-4 pt-16", "{account.name}" } })
+fn Profile<'a>(cx: Scope, account: &'a PublicAccount) -> Element {
+    let current_account = use_read(cx, ACCOUNT);
+    let is_other = match current_account {
+        Some(current_account) => current_account.id != account.id,
+        None => false,
+    };
+    let label = account.label();
+    let sc = cx.sc();
+    let account_id = account.id;
+    let fields = use_future(cx, &account_id, |account_id| async move {
+        api_result(account_profile_fields(sc, account_id).await).unwrap_or_default()
+    });
+    // the server's copy only covers the first render, later profiles are fetched
+    let ready = *use_read(cx, READY);
+    let server_fields = use_shared_state::<Option<ProfileFields>>(cx)
+        .and_then(|fields| fields.read().clone())
+        .filter(|_| !ready);
+    let ProfileFields {
+        bio,
+        website,
+        pronouns,
+    } = fields
+        .value()
+        .cloned()
+        .or(server_fields)
+        .unwrap_or_default();
+    let pronouns = pronouns.map(|p| format!(" · {p}")).unwrap_or_default();
+    let website = website.as_deref().and_then(models::sanitize_url);
+    cx.render(rsx! {
+        div { class: "flex flex-col gap-4 items-center p-4 pt-16",
+            div { class: "flex flex-col items-center",
+                h1 { class: "text-2xl text-center", "{label}" }
+                div { class: "text-sm opacity-60",
+                    a { href: "/profile/{account.name}", "@{account.name}" }
+                    "{pronouns}"
+                }
+            }
+            bio.iter().map(|bio| rsx! {
+                p { class: "max-w-md text-center whitespace-pre-line", "{bio}" }
+            })
+            website.iter().map(|website| rsx! {
+                a {
+                    class: "text-indigo-500 truncate max-w-md",
+                    href: "{website}",
+                    target: "_blank",
+                    rel: "noopener noreferrer nofollow",
+                    "{website}"
+                }
+            })
+            if is_other {
+                rsx! {
+                    RelationsLoader {}
+                    div { class: "flex gap-4",
+                        RelationButton { relation: Relation::Mute, account: (*account).clone() }
+                        RelationButton { relation: Relation::Block, account: (*account).clone() }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Relation {
+    Block,
+    Mute,
+}
+
+impl Relation {
+    fn accounts(self, relations: &AccountRelations) -> &Vec<PublicAccount> {
+        match self {
+            Relation::Block => &relations.blocked,
+            Relation::Mute => &relations.muted,
+        }
+    }
+
+    fn accounts_mut(self, relations: &mut AccountRelations) -> &mut Vec<PublicAccount> {
+        match self {
+            Relation::Block => &mut relations.blocked,
+            Relation::Mute => &mut relations.muted,
+        }
+    }
+}
+
+/// Fetches who the current account has blocked and muted into `RELATIONS`.
+fn RelationsLoader(cx: Scope) -> Element {
+    let relations = use_atom_state(cx, RELATIONS);
+    let sc = cx.sc();
+    use_future(cx, (), |_| {
+        to_owned![relations];
+        async move {
+            if let Ok(result) = api_result(relationships(sc).await) {
+                relations.set(Some(result));
+            }
+        }
+    });
+    None
+}
+
+#[inline_props]
+fn RelationButton(cx: Scope, relation: Relation, account: PublicAccount) -> Element {
+    let relations = use_atom_state(cx, RELATIONS);
+    let posts = use_atom_state(cx, POSTS);
+    let set_toast = use_set(cx, TOAST);
+    let relation = *relation;
+    let active = match relations.get() {
+        Some(r) => relation.accounts(r).iter().any(|a| a.id == account.id),
+        None => false,
+    };
+    let label = match (relation, active) {
+        (Relation::Block, true) => "Unblock",
+        (Relation::Block, false) => "Block",
+        (Relation::Mute, true) => "Unmute",
+        (Relation::Mute, false) => "Mute",
+    };
+    let onclick = move |_| {
+        to_owned![relations, posts, set_toast, account];
+        let sc = cx.sc();
+        cx.spawn(async move {
+            let result = match relation {
+                Relation::Block => set_blocked(sc, account.id, !active).await,
+                Relation::Mute => set_muted(sc, account.id, !active).await,
+            };
+            match api_result(result) {
+                Ok(_) => {
+                    relations.with_mut(|r| {
+                        let accounts =
+                            relation.accounts_mut(r.get_or_insert_with(Default::default));
+                        match active {
+                            true => accounts.retain(|a| a.id != account.id),
+                            false => accounts.push(account.clone()),
+                        }
+                    });
+                    if !active {
+                        posts.with_mut(|posts| posts.retain(|p| p.account_id != account.id));
+                    }
+                }
+                Err(err) => set_toast(Some(err)),
+            }
+        })
+    };
+    cx.render(rsx! {
+        button { class: "px-4 py-2 rounded-md dark:bg-gray-800 bg-gray-100", onclick: onclick, "{label}" }
+    })
+}
+
+fn RelationsList(cx: Scope) -> Element {
+    let relations = use_read(cx, RELATIONS);
+    let Some(relations) = relations else { return None };
+    let sections = [(Relation::Block, "Blocked"), (Relation::Mute, "Muted")];
+    cx.render(rsx! {
+        sections.iter().filter(|(relation, _)| !relation.accounts(relations).is_empty()).map(|(relation, title)| {
+            rsx! {
+                div { key: "{title}", class: "p-4 rounded-md dark:bg-gray-800 dark:text-white bg-gray-100 text-gray-950 flex flex-col gap-2",
+                    h2 { class: "text-lg", "{title}" }
+                    relation.accounts(relations).iter().map(|account| rsx! {
+                        div { key: "{account.id}", class: "flex justify-between items-center",
+                            div { "{account.name}" }
+                            RelationButton { relation: *relation, account: account.clone() }
+                        }
+                    })
+                }
+            }
+        })
+    })
 }
 
 fn Posts(cx: Scope) -> Element {
@@ -1211,6 +7207,7 @@ fn PostComponent(cx: Scope, post: Post, logged_in: bool) -> Element<'a> {
     let set_view = use_set(cx, VIEW);
     let posts = use_atom_state(cx, POSTS);
     let account = use_read(cx, ACCOUNT);
+    let set_toast = use_set(cx, TOAST);
     let liked_class = match post.liked_by_current_account {
         Some(_) => "text-red-500",
         None => "",
@@ -1224,7 +7221,7 @@ fn PostComponent(cx: Scope, post: Post, logged_in: bool) -> Element<'a> {
         set_frame_view(Frame::Drawer(View::Comments(post.clone())));
     };
     let on_like = move || {
-        to_owned![posts, account];
+        to_owned![posts, account, set_toast];
         let sc = cx.sc();
         let post_id = post.id;
         let account_id = account.unwrap().id;
@@ -1241,35 +7238,143 @@ fn PostComponent(cx: Scope, post: Post, logged_in: bool) -> Element<'a> {
             }
         });
         cx.spawn(async move {
-            if liked {
-                if let Ok(false) | Err(_) = dislike_post(sc, post_id).await {
-                    // something has gone wrong, revert to old state
-                    posts.set(old_posts);
-                }
+            let result = if liked {
+                api_result(dislike_post(sc, post_id).await).map(|_| ())
             } else {
-                if let Ok(None) | Err(_) = like_post(sc, post_id).await {
-                    // something has gone wrong, revert to old state
-                    posts.set(old_posts);
-                }
+                api_result(like_post(sc, post_id).await).map(|_| ())
+            };
+            if let Err(err) = result {
+                // something has gone wrong, revert to old state
+                posts.set(old_posts);
+                set_toast(Some(err));
+            }
+        });
+    };
+    let on_bookmark = move || {
+        to_owned![posts, set_toast];
+        let sc = cx.sc();
+        let post_id = post.id;
+        let account_id = account.unwrap().id;
+        let bookmarked = post.bookmarked_by_current_account.is_none();
+        posts.with_mut(|posts| {
+            let Some(post) = posts.iter_mut().find(|p| p.id == post_id) else { return };
+            post.bookmarked_by_current_account = bookmarked.then_some(account_id);
+        });
+        cx.spawn(async move {
+            if let Err(err) = api_result(set_bookmarked(sc, post_id, bookmarked).await) {
+                posts.with_mut(|posts| {
+                    let Some(post) = posts.iter_mut().find(|p| p.id == post_id) else { return };
+                    post.bookmarked_by_current_account = (!bookmarked).then_some(account_id);
+                });
+                set_toast(Some(err));
+            }
+        });
+    };
+    let bookmark_icon = match post.bookmarked_by_current_account {
+        Some(_) => &Icons::BookmarkFill,
+        None => &Icons::Bookmark,
+    };
+    let on_repost = move || {
+        to_owned![posts, set_toast];
+        let sc = cx.sc();
+        let post_id = post.id;
+        let reposted = post.reposted_by_current_account.is_none();
+        cx.spawn(async move {
+            match api_result(set_reposted(sc, post_id, reposted).await) {
+                Ok(post) => posts.with_mut(|posts| {
+                    if let Some(p) = posts.iter_mut().find(|p| p.id == post.id) {
+                        *p = Post {
+                            reposted_by: p.reposted_by.clone(),
+                            ..post
+                        };
+                    }
+                }),
+                Err(err) => set_toast(Some(err)),
             }
         });
     };
+    let reposted_class = match post.reposted_by_current_account {
+        Some(_) => "text-green-500",
+        None => "",
+    };
+    let repost_count = post.repost_count + post.quote_count;
+    let reposted_by = post.reposted_by.clone().unwrap_or_default();
+    let quoted_name = post.quoted_account_name.clone().unwrap_or_default();
+    let quoted_body = post.quoted_body.clone().unwrap_or_default();
     let comment_count = post.comment_count;
     cx.render(rsx! {
         div { class: "snap-center flex items-center justify-center flex-col relative h-full",
+            if !reposted_by.is_empty() {
+                rsx! { div { class: "absolute top-12 text-sm opacity-60", "Reposted by {reposted_by}" } }
+            }
+            if post.publish_at.is_some() {
+                rsx! { div { class: "absolute top-20 text-sm opacity-60", "Scheduled, only you can see this for now" } }
+            }
             div { class: "text-center text-2xl", "{post.body}" }
+            if post.quoted_body.is_some() {
+                rsx! { div { class: "mt-6 w-full max-w-sm px-4", QuotedPost { name: "{quoted_name}", body: "{quoted_body}" } } }
+            }
+            if post.attachments.is_some() {
+                rsx! { AttachmentStrip { filenames: post.attachment_names() } }
+            }
+            if post.poll.is_some() {
+                rsx! { PollComponent { post: post.clone(), logged_in: *logged_in } }
+            }
+            if first_url(&post.body).is_some() {
+                rsx! { LinkPreviewCard { post_id: post.id } }
+            }
             div { class: "flex flex-col gap-6 items-center absolute bottom-4 right-4 z-20 dark:bg-gray-950/70",
-                button { class: "opacity-80", onclick: move |_| {} }
                 button {
-                    class: "opacity-80 flex flex-col items-center",
+                    class: "opacity-80",
+                    onclick: move |_| {
+                        match logged_in {
+                            true => set_frame_view(Frame::Modal(View::Report(ReportTarget::Post(post.id)))),
+                            false => set_frame_view(Frame::Modal(View::Signup)),
+                        }
+                    },
+                    Icon { size: 32, icon: &Icons::Flag }
+                }
+                button {
+                    class: "opacity-80 flex flex-col items-center",
+                    onclick: move |_| {
+                        match logged_in {
+                            true => on_like(),
+                            false => set_frame_view(Frame::Modal(View::Signup)),
+                        }
+                    },
+                    div { class: "{liked_class}", Icon { size: 32, icon: &liked_icon } }
+                    div { "{like_count}" }
+                }
+                button {
+                    class: "opacity-80 flex flex-col items-center",
+                    onclick: move |_| {
+                        match logged_in {
+                            true => on_repost(),
+                            false => set_frame_view(Frame::Modal(View::Signup)),
+                        }
+                    },
+                    div { class: "{reposted_class}", Icon { size: 32, icon: &Icons::Repeat } }
+                    div { "{repost_count}" }
+                }
+                button {
+                    class: "opacity-80",
+                    onclick: move |_| {
+                        match logged_in {
+                            true => set_frame_view(Frame::Modal(View::Quote(post.clone()))),
+                            false => set_frame_view(Frame::Modal(View::Signup)),
+                        }
+                    },
+                    Icon { size: 32, icon: &Icons::Quotes }
+                }
+                button {
+                    class: "opacity-80",
                     onclick: move |_| {
                         match logged_in {
-                            true => on_like(),
+                            true => on_bookmark(),
                             false => set_frame_view(Frame::Modal(View::Signup)),
                         }
                     },
-                    div { class: "{liked_class}", Icon { size: 32, icon: &liked_icon } }
-                    div { "{like_count}" }
+                    Icon { size: 32, icon: bookmark_icon }
                 }
                 button {
                     class: "opacity-80",
@@ -1281,15 +7386,7 @@ fn PostComponent(cx: Scope, post: Post, logged_in: bool) -> Element<'a> {
                 }
                 button {
                     class: "opacity-80",
-                    onclick: move |_| {
-                        to_owned![post];
-                        let account = Account {
-                            name: post.account_name,
-                            id: post.account_id,
-                            ..Default::default()
-                        };
-                        set_view(View::Profile(account))
-                    },
+                    onclick: move |_| set_view(View::Profile(post.account())),
                     ProfilePhoto { account: post.account() }
                 }
             }
@@ -1298,7 +7395,66 @@ fn PostComponent(cx: Scope, post: Post, logged_in: bool) -> Element<'a> {
 }
 
 #[inline_props]
-fn ProfilePhoto(cx: Scope, account: Account) -> Element {
+fn QuotedPost<'a>(cx: Scope, name: &'a str, body: &'a str) -> Element {
+    cx.render(rsx! {
+        div { class: "rounded-md border border-gray-300 dark:border-gray-700 p-3 flex flex-col gap-1 text-left",
+            div { class: "text-sm opacity-60", "{name}" }
+            div { class: "line-clamp-4", "{body}" }
+        }
+    })
+}
+
+#[inline_props]
+fn LinkPreviewCard(cx: Scope, post_id: i64) -> Element {
+    let sc = cx.sc();
+    let post_id = *post_id;
+    let future = use_future(cx, &post_id, |_| async move {
+        api_result(post_link_preview(sc, post_id).await)
+            .ok()
+            .flatten()
+    });
+    let Some(Some(preview)) = future.value() else { return None };
+    let title = preview.title.clone().unwrap_or_default();
+    let description = preview.description.clone().unwrap_or_default();
+    let image_url = preview.image_url.clone().unwrap_or_default();
+    let url = &preview.url;
+    cx.render(rsx! {
+        a {
+            class: "mt-6 mx-4 max-w-sm rounded-md overflow-hidden flex flex-col border border-gray-300 dark:border-gray-700",
+            href: "{url}",
+            target: "_blank",
+            rel: "noopener noreferrer nofollow",
+            if !image_url.is_empty() {
+                rsx! { img { class: "w-full h-40 object-cover", src: "{image_url}", loading: "lazy" } }
+            }
+            div { class: "p-3 flex flex-col gap-1",
+                if !title.is_empty() {
+                    rsx! { div { class: "font-bold line-clamp-2", "{title}" } }
+                }
+                if !description.is_empty() {
+                    rsx! { div { class: "text-sm opacity-60 line-clamp-3", "{description}" } }
+                }
+            }
+        }
+    })
+}
+
+#[inline_props]
+fn AttachmentStrip<'a>(cx: Scope, filenames: Vec<&'a str>) -> Element {
+    let images = filenames.iter().map(|filename| {
+        rsx! {
+            a { key: "{filename}", class: "snap-center shrink-0", href: "/uploads/{filename}", target: "_blank",
+                img { class: "h-48 rounded-md object-cover", src: "/uploads/thumb-{filename}", loading: "lazy" }
+            }
+        }
+    });
+    cx.render(rsx! {
+        div { class: "snap-x snap-mandatory overflow-x-auto flex gap-2 max-w-full px-4 mt-6", images }
+    })
+}
+
+#[inline_props]
+fn ProfilePhoto(cx: Scope, account: PublicAccount) -> Element {
     let initial = account.initial();
     cx.render(rsx! {
         div {
@@ -1311,17 +7467,21 @@ fn ProfilePhoto(cx: Scope, account: Account) -> Element {
 #[inline_props]
 fn Comments<'a>(cx: Scope, post: &'a Post) -> Element {
     let comments_state = use_atom_state(cx, COMMENTS);
+    let set_toast = use_set(cx, TOAST);
     let sc = cx.sc();
     let post_id = post.id;
     let future = use_future(cx, &post_id, |_| {
-        to_owned![comments_state];
+        to_owned![comments_state, set_toast];
         async move {
-            match comments_by_post_id(sc, post_id).await {
+            match api_result(comments_by_post_id(sc, post_id).await) {
                 Ok(c) => {
                     comments_state.set(c.clone());
                     c
                 }
-                Err(_) => vec![],
+                Err(err) => {
+                    set_toast(Some(err));
+                    vec![]
+                }
             }
         }
     });
@@ -1361,6 +7521,14 @@ fn Comments<'a>(cx: Scope, post: &'a Post) -> Element {
 #[inline_props]
 fn CommentComponent<'a>(cx: Scope, comment: &'a Comment) -> Element {
     let account = comment.account();
+    let logged_in = use_read(cx, ACCOUNT).is_some();
+    let set_frame_view = use_set(cx, FRAME_VIEW);
+    let on_report = move |_| match logged_in {
+        true => set_frame_view(Frame::Modal(View::Report(ReportTarget::Comment(
+            comment.id,
+        )))),
+        false => set_frame_view(Frame::Modal(View::Signup)),
+    };
     cx.render(rsx! {
         div {
             class: "grid grid-cols-6",
@@ -1375,6 +7543,7 @@ fn CommentComponent<'a>(cx: Scope, comment: &'a Comment) -> Element {
                     div { "{comment.account_name}" }
                     div { "-" }
                     div { "{comment.created_at}" }
+                    button { class: "ml-auto text-sm opacity-60", onclick: on_report, "Report" }
                 }
                 div { "{comment.body}" }
             }
@@ -1382,28 +7551,288 @@ fn CommentComponent<'a>(cx: Scope, comment: &'a Comment) -> Element {
     })
 }
 
+#[inline_props]
+fn ReportComponent(cx: Scope, target: ReportTarget) -> Element {
+    let set_toast = use_set(cx, TOAST);
+    let reported = use_state(cx, || false);
+    let target = *target;
+    let on_report = move |reason: ReportReason| {
+        to_owned![set_toast, reported];
+        let sc = cx.sc();
+        cx.spawn(async move {
+            match api_result(report(sc, target, reason).await) {
+                Ok(_) => reported.set(true),
+                Err(err) => set_toast(Some(err)),
+            }
+        })
+    };
+    let content = match reported.get() {
+        true => rsx! {
+            p { class: "text-center", "Thanks, a moderator will take a look" }
+        },
+        false => rsx! {
+            ReportReason::ALL.iter().map(|reason| {
+                let reason = *reason;
+                rsx! {
+                    button {
+                        key: "{reason.as_str()}",
+                        class: "p-4 rounded-md dark:bg-gray-800 bg-gray-100 text-left",
+                        onclick: move |_| on_report(reason),
+                        "{reason.label()}"
+                    }
+                }
+            })
+        },
+    };
+    cx.render(rsx! {
+        div { class: "p-4 flex flex-col gap-4",
+            h1 { class: "text-xl text-center", "Why are you reporting this {target.kind()}?" }
+            content
+        }
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum NewPostPanel {
+    Form,
+    Drafts,
+    Scheduled,
+}
+
+/// `datetime-local` inputs are in local time without an offset, the
+/// browser knows the offset so it does the parsing.
+#[allow(unreachable_code)]
+fn parse_local_datetime(value: &str) -> Option<i64> {
+    #[cfg(frontend)]
+    {
+        let millis = js_sys::Date::parse(value);
+        return (!millis.is_nan()).then(|| (millis / 1000.0) as i64);
+    }
+
+    #[cfg(backend)]
+    {
+        let _ = value;
+        None
+    }
+}
+
+/// The other way around, seconds to what a `datetime-local` input shows.
+#[allow(unreachable_code)]
+fn format_local_datetime(seconds: i64) -> String {
+    #[cfg(frontend)]
+    {
+        let date = js_sys::Date::new(&wasm_bindgen::JsValue::from_f64(seconds as f64 * 1000.0));
+        return format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}",
+            date.get_full_year(),
+            date.get_month() + 1,
+            date.get_date(),
+            date.get_hours(),
+            date.get_minutes()
+        );
+    }
+
+    #[cfg(backend)]
+    {
+        let _ = seconds;
+        String::new()
+    }
+}
+
+#[inline_props]
+fn ScheduledList<'a>(cx: Scope, onselect: EventHandler<'a, Post>) -> Element {
+    let set_toast = use_set(cx, TOAST);
+    let posts_state = use_atom_state(cx, POSTS);
+    let posts: &UseState<Option<Vec<Post>>> = use_state(cx, || None);
+    let sc = cx.sc();
+    use_future(cx, (), |_| {
+        to_owned![posts, set_toast];
+        async move {
+            match api_result(scheduled_posts(sc).await) {
+                Ok(p) => posts.set(Some(p)),
+                Err(err) => set_toast(Some(err)),
+            }
+        }
+    });
+    let on_cancel = move |post_id: i64| {
+        to_owned![posts, posts_state, set_toast];
+        let sc = cx.sc();
+        cx.spawn(async move {
+            match api_result(cancel_scheduled_post(sc, post_id).await) {
+                Ok(_) => {
+                    posts.with_mut(|p| {
+                        if let Some(p) = p {
+                            p.retain(|post| post.id != post_id)
+                        }
+                    });
+                    posts_state.with_mut(|p| p.retain(|post| post.id != post_id));
+                }
+                Err(err) => set_toast(Some(err)),
+            }
+        })
+    };
+    let Some(posts) = posts.get() else { return cx.render(rsx! { Loading {} }) };
+    if posts.is_empty() {
+        return cx.render(rsx! { p { class: "text-center opacity-60", "Nothing scheduled" } });
+    }
+    cx.render(rsx! {
+        div { class: "flex flex-col gap-4",
+            posts.iter().map(|post| {
+                let post_id = post.id;
+                let publish_at = format_local_datetime(post.publish_at.unwrap_or_default())
+                    .replace('T', " ");
+                rsx! {
+                    div { key: "{post.id}", class: "flex justify-between gap-4 p-4 rounded-md dark:bg-gray-800 bg-gray-100",
+                        button { class: "flex flex-col text-left truncate", onclick: move |_| onselect.call(post.clone()),
+                            span { class: "text-sm opacity-60", "{publish_at}" }
+                            span { "{post.body}" }
+                        }
+                        button { class: "text-red-500", onclick: move |_| on_cancel(post_id), "Cancel" }
+                    }
+                }
+            })
+        }
+    })
+}
+
+fn SavedPostList(cx: Scope) -> Element {
+    let set_toast = use_set(cx, TOAST);
+    let set_frame_view = use_set(cx, FRAME_VIEW);
+    let posts: &UseState<Vec<Post>> = use_state(cx, Vec::new);
+    let before: &UseState<Option<i64>> = use_state(cx, || None);
+    let has_more = use_state(cx, || false);
+    let sc = cx.sc();
+    let current_before = *before.get();
+    let future = use_future(cx, &current_before, |before| {
+        to_owned![posts, has_more, set_toast];
+        async move {
+            match api_result(saved_posts(sc, before).await) {
+                Ok(page) => {
+                    has_more.set(page.has_more);
+                    posts.with_mut(|posts| posts.extend(page.posts));
+                }
+                Err(err) => set_toast(Some(err)),
+            }
+        }
+    });
+    let on_unsave = move |post_id: i64| {
+        to_owned![posts, set_toast];
+        let sc = cx.sc();
+        cx.spawn(async move {
+            match api_result(set_bookmarked(sc, post_id, false).await) {
+                Ok(_) => posts.with_mut(|p| p.retain(|post| post.id != post_id)),
+                Err(err) => set_toast(Some(err)),
+            }
+        })
+    };
+    let loading = future.value().is_none();
+    cx.render(rsx! {
+        div { class: "max-w-md mx-auto flex flex-col gap-4 p-4",
+            h1 { class: "text-2xl text-gray-950 dark:text-white text-center", "Saved posts" }
+            if posts.is_empty() && !loading {
+                rsx! { p { class: "text-center opacity-60", "Nothing saved yet" } }
+            }
+            posts.iter().map(|post| {
+                let post_id = post.id;
+                rsx! {
+                    div { key: "{post.id}", class: "flex justify-between gap-4 p-4 rounded-md dark:bg-gray-800 bg-gray-100",
+                        button {
+                            class: "flex flex-col text-left truncate",
+                            onclick: move |_| set_frame_view(Frame::Drawer(View::Comments(post.clone()))),
+                            span { class: "text-sm opacity-60", "{post.account_name}" }
+                            span { "{post.body}" }
+                        }
+                        button { class: "text-red-500", onclick: move |_| on_unsave(post_id), "Unsave" }
+                    }
+                }
+            })
+            if loading {
+                rsx! { Loading {} }
+            } else if **has_more {
+                rsx! { Button { onclick: move |_| before.set(posts.last().and_then(|post| post.bookmark_id)), "Load more" } }
+            }
+        }
+    })
+}
+
+#[inline_props]
+fn DraftList<'a>(cx: Scope, onselect: EventHandler<'a, Draft>) -> Element {
+    let set_toast = use_set(cx, TOAST);
+    let drafts: &UseState<Option<Vec<Draft>>> = use_state(cx, || None);
+    let sc = cx.sc();
+    use_future(cx, (), |_| {
+        to_owned![drafts, set_toast];
+        async move {
+            match api_result(list_drafts(sc).await) {
+                Ok(d) => drafts.set(Some(d)),
+                Err(err) => set_toast(Some(err)),
+            }
+        }
+    });
+    let on_delete = move |draft_id: i64| {
+        to_owned![drafts, set_toast];
+        let sc = cx.sc();
+        cx.spawn(async move {
+            match api_result(delete_draft(sc, draft_id).await) {
+                Ok(_) => drafts.with_mut(|d| {
+                    if let Some(d) = d {
+                        d.retain(|draft| draft.id != draft_id)
+                    }
+                }),
+                Err(err) => set_toast(Some(err)),
+            }
+        })
+    };
+    let Some(drafts) = drafts.get() else { return cx.render(rsx! { Loading {} }) };
+    if drafts.is_empty() {
+        return cx.render(rsx! { p { class: "text-center opacity-60", "No drafts yet" } });
+    }
+    cx.render(rsx! {
+        div { class: "flex flex-col gap-4",
+            drafts.iter().map(|draft| {
+                let draft_id = draft.id;
+                rsx! {
+                    div { key: "{draft.id}", class: "flex justify-between gap-4 p-4 rounded-md dark:bg-gray-800 bg-gray-100",
+                        button { class: "text-left truncate", onclick: move |_| onselect.call(draft.clone()), "{draft.body}" }
+                        button { class: "text-red-500", onclick: move |_| on_delete(draft_id), "Delete" }
+                    }
+                }
+            })
+        }
+    })
+}
+
 #[inline_props]
 fn NewComment<'a>(cx: Scope, post: &'a Post) -> Element {
     let account = use_read(cx, ACCOUNT);
     let set_frame_view = use_set(cx, FRAME_VIEW);
     let comments = use_atom_state(cx, COMMENTS);
     let posts = use_atom_state(cx, POSTS);
+    let set_toast = use_set(cx, TOAST);
     let body = use_state(cx, || "".to_string());
     let onadd = move |_| {
         if account.is_none() {
             return set_frame_view(Frame::Modal(View::Signup));
         }
-        to_owned![comments, posts];
+        if let Some(message) =
+            validation::validate_body(body.get(), validation::COMMENT_BODY).message()
+        {
+            return set_toast(Some(ApiError::validation("body", &message)));
+        }
+        to_owned![comments, posts, set_toast];
         let sc = cx.sc();
         let body = body.get().clone();
         let post_id = post.id;
         cx.spawn(async move {
-            if let Ok(Some(comment)) = leave_comment(sc, post_id, body).await {
-                comments.with_mut(|comments| comments.push(comment));
-                posts.with_mut(|posts| {
-                    let Some(post) = posts.into_iter().find(|p| p.id == post_id) else { return };
-                    post.comment_count = post.comment_count + 1;
-                });
+            match api_result(leave_comment(sc, post_id, body).await) {
+                Ok(comment) => {
+                    comments.with_mut(|comments| comments.push(comment));
+                    posts.with_mut(|posts| {
+                        let Some(post) = posts.into_iter().find(|p| p.id == post_id) else { return };
+                        post.comment_count = post.comment_count + 1;
+                    });
+                }
+                Err(err) => set_toast(Some(err)),
             }
         })
     };
@@ -1412,38 +7841,390 @@ fn NewComment<'a>(cx: Scope, post: &'a Post) -> Element {
             class: "flex flex-col gap-8",
             div {
                 class: "flex flex-col gap-4",
-                TextArea { name: "body", oninput: move |e: FormEvent| body.set(e.value.clone()) }
+                TextArea {
+                    name: "body",
+                    rules: validation::COMMENT_BODY,
+                    oninput: move |e: FormEvent| body.set(e.value.clone())
+                }
                 Button { onclick: onadd, "Leave comment" }
             }
         }
     )
 }
 
-fn NewPost(cx: Scope) -> Element {
+#[inline_props]
+fn NewPost<'a>(cx: Scope, quoted: Option<&'a Post>) -> Element {
     let posts_state = use_atom_state(cx, POSTS);
     let frame_view = use_atom_state(cx, FRAME_VIEW);
+    let set_toast = use_set(cx, TOAST);
     let body = use_state(cx, || "".to_string());
+    let draft_id: &UseState<Option<i64>> = use_state(cx, || None);
+    let saving_draft = use_state(cx, || false);
+    // set once the post is sent, the server deletes the draft and a late
+    // autosave would bring it back
+    let submitted = use_state(cx, || false);
+    let publish_at: &UseState<Option<i64>> = use_state(cx, || None);
+    let editing: &UseState<Option<i64>> = use_state(cx, || None);
+    let attachments: &UseState<Vec<Attachment>> = use_state(cx, Vec::new);
+    let poll: &UseState<Option<NewPoll>> = use_state(cx, || None);
+    let quoted_post_id = quoted.map(|post| post.id);
+    let uploading = use_state(cx, || false);
+    let panel = use_state(cx, || NewPostPanel::Form);
+    let oninput = move |e: FormEvent| {
+        let typed = e.value.clone();
+        body.set(e.value.clone());
+        if typed.trim().is_empty() || editing.is_some() {
+            return;
+        }
+        let sc = cx.sc();
+        cx.spawn({
+            to_owned![body, draft_id, saving_draft, submitted, set_toast];
+            async move {
+                TimeoutFuture::new(1000).await;
+                // a save that's still running picks up the latest body when it's done,
+                // starting another one before it has a draft id would make a second draft
+                if typed != *body.current() || *saving_draft.current() || *submitted.current() {
+                    return;
+                }
+                saving_draft.set(true);
+                let mut unsaved = typed;
+                loop {
+                    match api_result(
+                        save_draft(sc.clone(), *draft_id.current(), unsaved.clone()).await,
+                    ) {
+                        Ok(draft) => draft_id.set(Some(draft.id)),
+                        Err(err) => {
+                            set_toast(Some(err));
+                            break;
+                        }
+                    }
+                    let latest = body.current().to_string();
+                    if latest == unsaved || latest.trim().is_empty() || *submitted.current() {
+                        break;
+                    }
+                    unsaved = latest;
+                }
+                saving_draft.set(false);
+            }
+        })
+    };
+    let on_pick_images = move |e: FormEvent| {
+        let Some(files) = e.files.clone() else { return };
+        let sc = cx.sc();
+        to_owned![attachments, uploading, set_toast];
+        cx.spawn(async move {
+            uploading.set(true);
+            let room = ATTACHMENTS_PER_POST.saturating_sub(attachments.current().len());
+            for name in files.files().into_iter().take(room) {
+                let Some(data) = files.read_file(&name).await else { continue };
+                match api_result(upload_image(sc.clone(), data).await) {
+                    Ok(attachment) => attachments.with_mut(|a| a.push(attachment)),
+                    Err(err) => set_toast(Some(err)),
+                }
+            }
+            uploading.set(false);
+        });
+    };
+    let on_select_draft = move |draft: Draft| {
+        body.set(draft.body);
+        draft_id.set(Some(draft.id));
+        editing.set(None);
+        panel.set(NewPostPanel::Form);
+    };
+    let on_select_scheduled = move |post: Post| {
+        body.set(post.body);
+        publish_at.set(post.publish_at);
+        draft_id.set(None);
+        editing.set(Some(post.id));
+        panel.set(NewPostPanel::Form);
+    };
     let on_add = move |_| {
-        to_owned![body, posts_state, frame_view];
+        if let Some(message) =
+            validation::validate_body(body.get(), validation::POST_BODY).message()
+        {
+            return set_toast(Some(ApiError::validation("body", &message)));
+        }
+        submitted.set(true);
+        to_owned![
+            body,
+            draft_id,
+            saving_draft,
+            submitted,
+            publish_at,
+            editing,
+            attachments,
+            poll,
+            posts_state,
+            frame_view,
+            set_toast
+        ];
         let sc = cx.sc();
         cx.spawn(async move {
-            match add_post(sc, body.get().clone()).await {
-                Ok(Some(new_post)) => {
-                    posts_state.with_mut(|p| p.insert(0, new_post));
+            // let a running save finish so it can't land after the draft is deleted
+            while *saving_draft.current() {
+                TimeoutFuture::new(50).await;
+            }
+            let body = body.get().clone();
+            let result = match *editing.get() {
+                Some(post_id) => update_scheduled_post(sc, post_id, body, *publish_at.get()).await,
+                None => {
+                    let attachment_ids = attachments.get().iter().map(|a| a.id).collect();
+                    add_post(
+                        sc,
+                        body,
+                        *draft_id.current(),
+                        *publish_at.get(),
+                        attachment_ids,
+                        poll.get().clone(),
+                        quoted_post_id,
+                    )
+                    .await
+                }
+            };
+            match api_result(result) {
+                Ok(new_post) => {
+                    posts_state.with_mut(|posts| {
+                        posts.retain(|p| p.id != new_post.id);
+                        posts.insert(0, new_post);
+                    });
                     frame_view.set(Frame::Empty);
                 }
-                Ok(None) => todo!(),
-                Err(err) => log::info!("{}", err),
+                Err(ApiError::Unauthenticated) => {
+                    submitted.set(false);
+                    frame_view.set(Frame::Modal(View::Login));
+                }
+                Err(err) => {
+                    submitted.set(false);
+                    set_toast(Some(err));
+                }
+            }
+        });
+    };
+    let submit_label = match (editing.is_some(), publish_at.is_some()) {
+        (true, _) => "Save scheduled post",
+        (false, true) => "Schedule post",
+        (false, false) => "Add post",
+    };
+    let publish_at_value = publish_at
+        .get()
+        .map(format_local_datetime)
+        .unwrap_or_default();
+    cx.render(rsx! {
+        div { class: "flex flex-col gap-8 p-4",
+            div { class: "flex justify-between items-center pr-12",
+                h1 { class: "text-2xl", "New post" }
+                div { class: "flex gap-4",
+                    button { class: "underline", onclick: move |_| panel.set(NewPostPanel::Drafts), "Drafts" }
+                    button { class: "underline", onclick: move |_| panel.set(NewPostPanel::Scheduled), "Scheduled" }
+                }
+            }
+            match panel.get() {
+                NewPostPanel::Drafts => rsx! { DraftList { onselect: on_select_draft } },
+                NewPostPanel::Scheduled => rsx! { ScheduledList { onselect: on_select_scheduled } },
+                NewPostPanel::Form => rsx! {
+                    div { class: "flex flex-col gap-4",
+                        TextArea { name: "body", value: body.get().as_str(), rules: validation::POST_BODY, oninput: oninput }
+                        quoted.iter().map(|post| rsx! {
+                            QuotedPost { name: "{post.account_name}", body: "{post.body}" }
+                        })
+                        label { class: "flex flex-col gap-1",
+                            span { class: "text-sm opacity-60", "Publish later (optional)" }
+                            input {
+                                r#type: "datetime-local",
+                                class: "p-3 rounded-md bg-white outline-none border border-gray-300 dark:border-gray-600 dark:bg-gray-700 dark:text-white text-gray-950",
+                                value: "{publish_at_value}",
+                                oninput: move |e: FormEvent| publish_at.set(parse_local_datetime(&e.value))
+                            }
+                        }
+                        if editing.is_none() {
+                            rsx! { PollForm { poll: poll } }
+                        }
+                        if editing.is_none() {
+                            rsx! {
+                                label { class: "flex flex-col gap-1",
+                                    span { class: "text-sm opacity-60", "Images (up to {ATTACHMENTS_PER_POST})" }
+                                    input {
+                                        r#type: "file",
+                                        accept: "image/jpeg,image/png,image/gif,image/webp",
+                                        multiple: true,
+                                        disabled: attachments.len() >= ATTACHMENTS_PER_POST || **uploading,
+                                        onchange: on_pick_images
+                                    }
+                                }
+                                div { class: "flex gap-2",
+                                    attachments.iter().map(|a| rsx! {
+                                        img { key: "{a.id}", class: "w-16 h-16 rounded-md object-cover", src: "/uploads/thumb-{a.filename}" }
+                                    })
+                                    if **uploading {
+                                        rsx! { Loading {} }
+                                    }
+                                }
+                            }
+                        }
+                        Button { onclick: on_add, "{submit_label}" }
+                    }
+                },
+            }
+        }
+    })
+}
+
+const POLL_DURATIONS: [(i64, &str); 4] = [
+    (60 * 60, "1 hour"),
+    (24 * 60 * 60, "1 day"),
+    (3 * 24 * 60 * 60, "3 days"),
+    (7 * 24 * 60 * 60, "7 days"),
+];
+
+#[inline_props]
+fn PollForm<'a>(cx: Scope, poll: &'a UseState<Option<NewPoll>>) -> Element {
+    let Some(current) = poll.get() else {
+        return cx.render(rsx! {
+            button {
+                class: "self-start underline",
+                onclick: move |_| {
+                    poll.set(Some(NewPoll {
+                        options: vec![String::new(), String::new()],
+                        closes_in: POLL_DURATIONS[1].0,
+                    }))
+                },
+                "Add a poll"
+            }
+        });
+    };
+    let option_count = current.options.len();
+    let options = current.options.iter().enumerate().map(|(i, option)| {
+        rsx! {
+            input {
+                key: "{i}",
+                r#type: "text",
+                class: "p-3 rounded-md bg-white outline-none border border-gray-300 dark:border-gray-600 dark:bg-gray-700 dark:text-white text-gray-950",
+                placeholder: "Option {i + 1}",
+                value: "{option}",
+                oninput: move |e: FormEvent| {
+                    poll.with_mut(|poll| {
+                        if let Some(poll) = poll {
+                            poll.options[i] = e.value.clone();
+                        }
+                    })
+                }
+            }
+        }
+    });
+    let durations = POLL_DURATIONS.iter().map(|(seconds, label)| {
+        rsx! { option { key: "{seconds}", value: "{seconds}", selected: *seconds == current.closes_in, "{label}" } }
+    });
+    cx.render(rsx! {
+        div { class: "flex flex-col gap-2",
+            div { class: "flex justify-between text-sm opacity-60",
+                span { "Poll" }
+                button { class: "underline", onclick: move |_| poll.set(None), "Remove poll" }
+            }
+            options
+            if option_count < 4 {
+                rsx! {
+                    button {
+                        class: "self-start underline text-sm",
+                        onclick: move |_| {
+                            poll.with_mut(|poll| {
+                                if let Some(poll) = poll {
+                                    poll.options.push(String::new());
+                                }
+                            })
+                        },
+                        "Add an option"
+                    }
+                }
+            }
+            label { class: "flex gap-2 items-center text-sm",
+                span { class: "opacity-60", "Closes after" }
+                select {
+                    class: "p-2 rounded-md bg-white border border-gray-300 dark:border-gray-600 dark:bg-gray-700",
+                    onchange: move |e: FormEvent| {
+                        let closes_in = e.value.parse().unwrap_or(POLL_DURATIONS[1].0);
+                        poll.with_mut(|poll| {
+                            if let Some(poll) = poll {
+                                poll.closes_in = closes_in;
+                            }
+                        })
+                    },
+                    durations
+                }
+            }
+        }
+    })
+}
+
+#[inline_props]
+fn PollComponent(cx: Scope, post: Post, logged_in: bool) -> Element {
+    let posts = use_atom_state(cx, POSTS);
+    let set_frame_view = use_set(cx, FRAME_VIEW);
+    let set_toast = use_set(cx, TOAST);
+    let post_id = post.id;
+    let options = post.poll_options();
+    let show_results = options.iter().any(|option| option.votes.is_some());
+    let total: i64 = options.iter().filter_map(|option| option.votes).sum();
+    let on_vote = move |option_id: i64| {
+        to_owned![posts, set_toast];
+        let sc = cx.sc();
+        cx.spawn(async move {
+            match api_result(vote_poll(sc, post_id, option_id).await) {
+                Ok(post) => posts.with_mut(|posts| {
+                    if let Some(p) = posts.iter_mut().find(|p| p.id == post.id) {
+                        *p = post;
+                    }
+                }),
+                Err(err) => set_toast(Some(err)),
             }
         });
     };
+    let options = options.into_iter().map(|option| {
+        let PollOption { id, body, votes } = option;
+        match show_results {
+            true => {
+                let votes = votes.unwrap_or(0);
+                let percent = match total {
+                    0 => 0,
+                    _ => votes * 100 / total,
+                };
+                let mine = match post.poll_vote == Some(id) {
+                    true => "font-bold",
+                    false => "",
+                };
+                rsx! {
+                    div { key: "{id}", class: "relative rounded-md overflow-hidden border border-gray-300 dark:border-gray-700 {mine}",
+                        div { class: "absolute inset-y-0 left-0 bg-indigo-500/30", style: "width: {percent}%" }
+                        div { class: "relative flex justify-between gap-4 px-3 py-2",
+                            span { "{body}" }
+                            span { "{percent}%" }
+                        }
+                    }
+                }
+            }
+            false => rsx! {
+                button {
+                    key: "{id}",
+                    class: "rounded-md border border-gray-300 dark:border-gray-700 px-3 py-2 text-left",
+                    onclick: move |_| {
+                        match logged_in {
+                            true => on_vote(id),
+                            false => set_frame_view(Frame::Modal(View::Signup)),
+                        }
+                    },
+                    "{body}"
+                }
+            },
+        }
+    });
+    let status = match (show_results, post.poll_vote.is_some()) {
+        (false, _) => "Vote to see the results".to_string(),
+        (true, true) => format!("{total} votes"),
+        (true, false) => format!("{total} votes, poll closed"),
+    };
     cx.render(rsx! {
-        div { class: "flex flex-col gap-8 p-4",
-            h1 { class: "text-2xl", "New post" }
-            div { class: "flex flex-col gap-4",
-                TextArea { name: "body", oninput: move |e: FormEvent| body.set(e.value.clone()) }
-                Button { onclick: on_add, "Add post" }
-            }
+        div { class: "flex flex-col gap-2 w-full max-w-sm px-4 mt-6",
+            options
+            div { class: "text-sm opacity-60", "{status}" }
         }
     })
 }
@@ -1459,44 +8240,82 @@ fn Signup(cx: Scope) -> Element {
     let view_state = use_atom_state(cx, VIEW);
     let frame_view = use_atom_state(cx, FRAME_VIEW);
     let account_state = use_atom_state(cx, ACCOUNT);
+    let login_code_state = use_atom_state(cx, LOGIN_CODE);
+    let set_toast = use_set(cx, TOAST);
     let signup_state = use_state(cx, || SignupState::default());
     let oninput = move |e: FormEvent| {
-        to_owned![signup_state];
+        let name = e.value.clone();
+        let signup_name = validate_name(&name);
         signup_state.with_mut(|st| {
-            st.name = e.value.clone();
-            st.signup_name = validate_name(&e.value);
+            st.name = name.clone();
+            st.signup_name = signup_name;
         });
+        if !signup_name.is_valid() {
+            return;
+        }
+        let sc = cx.sc();
+        cx.spawn({
+            to_owned![signup_state];
+            async move {
+                TimeoutFuture::new(300).await;
+                if signup_state.current().name != name {
+                    return;
+                }
+                // a failed check leaves the badge alone, signup checks again anyway
+                if let Ok(checked) = api_result(check_name(sc, name.clone()).await) {
+                    signup_state.with_mut(|st| {
+                        if st.name == name {
+                            st.signup_name = checked;
+                        }
+                    });
+                }
+            }
+        })
     };
     let onclick = move |_| {
         let sc = cx.sc();
-        to_owned![signup_state, account_state, view_state, frame_view];
+        to_owned![
+            signup_state,
+            account_state,
+            login_code_state,
+            view_state,
+            frame_view,
+            set_toast
+        ];
         cx.spawn({
             async move {
                 signup_state.with_mut(|state| state.loading = true);
-                let result = signup(sc, signup_state.name.clone()).await;
+                let result = api_result(signup(sc.clone(), signup_state.name.clone()).await);
                 match result {
-                    Ok(Ok(account)) => {
+                    Ok(account) => {
                         account_state.set(Some(account));
+                        match api_result(rotate_login_code(sc).await) {
+                            Ok(login_code) => login_code_state.set(Some(login_code)),
+                            Err(err) => set_toast(Some(err)),
+                        }
                         view_state.set(View::ShowAccount);
                         frame_view.set(Frame::Empty);
                     }
-                    Ok(Err(sn)) => {
+                    Err(err) => {
                         signup_state.with_mut(|st| {
                             st.loading = false;
-                            st.signup_name = sn;
+                            if let ApiError::Conflict(_) = err {
+                                st.signup_name.is_available = SignupNameState::Invalid;
+                            }
                         });
+                        set_toast(Some(err));
                     }
-                    Err(err) => log::info!("{err}"),
                 }
             }
         })
     };
     let SignupName {
-        is_alphanumeric,
+        has_allowed_chars,
+        symbols_between_letters,
         less_than_max_len,
         greater_than_min_len,
+        is_not_reserved,
         is_available,
-        ..
     } = signup_state.signup_name;
     render!(
         div { class: "max-w-md mx-auto flex flex-col gap-8 p-4",
@@ -1507,7 +8326,9 @@ fn Signup(cx: Scope) -> Element {
                 div { class: "flex flex-wrap gap-2",
                     Badge { color: "{greater_than_min_len}", text: "Min 3 chars" }
                     Badge { color: "{less_than_max_len}", text: "Max 20 chars" }
-                    Badge { color: "{is_alphanumeric}", text: "Letters and numbers" }
+                    Badge { color: "{has_allowed_chars}", text: "Letters, numbers, _ and ." }
+                    Badge { color: "{symbols_between_letters}", text: "_ and . between letters" }
+                    Badge { color: "{is_not_reserved}", text: "Not reserved" }
                     if signup_state.loading {
                         rsx! {
                             Badge { color: "gray", text: "..." }
@@ -1547,6 +8368,29 @@ impl Display for SignupNameState {
     }
 }
 
+fn Toast(cx: Scope) -> Element {
+    let toast = use_atom_state(cx, TOAST);
+    use_effect(cx, (toast.get().clone(),), |(error,)| {
+        to_owned![toast];
+        async move {
+            if error.is_some() {
+                TimeoutFuture::new(4000).await;
+                if *toast.current() == error {
+                    toast.set(None);
+                }
+            }
+        }
+    });
+    let Some(error) = toast.get() else { return None };
+    cx.render(rsx! {
+        div {
+            class: "fixed top-4 left-4 right-4 max-w-md mx-auto z-50 p-4 rounded-md shadow-md cursor-pointer dark:bg-red-500/90 bg-red-50 dark:text-white text-red-600 ring-1 ring-inset ring-red-500/20",
+            onclick: move |_| toast.set(None),
+            "{error}"
+        }
+    })
+}
+
 #[inline_props]
 fn Badge<'a>(cx: Scope, color: &'a str, text: &'a str) -> Element {
     let color_class = match *color {
@@ -1568,6 +8412,7 @@ fn Login(cx: Scope) -> Element {
     let frame_view = use_atom_state(cx, FRAME_VIEW);
     let account_state = use_atom_state(cx, ACCOUNT);
     let posts_state = use_atom_state(cx, POSTS);
+    let set_toast = use_set(cx, TOAST);
     let onclick = move |_| {
         let login_code = login_code.get().clone();
         let sx = cx.sc();
@@ -1576,20 +8421,20 @@ fn Login(cx: Scope) -> Element {
             account_state,
             error_state,
             frame_view,
-            posts_state
+            posts_state,
+            set_toast
         ];
         cx.spawn({
             async move {
-                if let Ok(account) = login(sx, login_code).await {
-                    match account {
-                        Some((account, posts)) => {
-                            account_state.set(Some(account));
-                            view_state.set(View::ShowAccount);
-                            frame_view.set(Frame::Empty);
-                            posts_state.set(posts);
-                        }
-                        None => error_state.set("No username found. Wanna take it?"),
+                match api_result(login(sx, login_code).await) {
+                    Ok((account, posts)) => {
+                        account_state.set(Some(account));
+                        view_state.set(View::ShowAccount);
+                        frame_view.set(Frame::Empty);
+                        posts_state.set(posts);
                     }
+                    Err(ApiError::NotFound) => error_state.set("No username found. Wanna take it?"),
+                    Err(err) => set_toast(Some(err)),
                 }
             }
         })
@@ -1615,22 +8460,320 @@ fn Login(cx: Scope) -> Element {
     })
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum AdminTab {
+    Reports,
+    Posts,
+    Comments,
+    Accounts,
+    Log,
+}
+
+fn Admin(cx: Scope) -> Element {
+    let tab = use_state(cx, || AdminTab::Reports);
+    let tabs = [
+        (AdminTab::Reports, "Reports"),
+        (AdminTab::Posts, "Posts"),
+        (AdminTab::Comments, "Comments"),
+        (AdminTab::Accounts, "Accounts"),
+        (AdminTab::Log, "Log"),
+    ];
+    let content = match tab.get() {
+        AdminTab::Reports => rsx! { ReportQueueList {} },
+        AdminTab::Posts => rsx! { AdminPostList {} },
+        AdminTab::Comments => rsx! { AdminCommentList {} },
+        AdminTab::Accounts => rsx! { AdminAccountList {} },
+        AdminTab::Log => rsx! { ModerationLogList {} },
+    };
+    cx.render(rsx! {
+        div { class: "max-w-md mx-auto flex flex-col gap-4 pt-16 px-4 md:px-0 min-h-screen",
+            h1 { class: "text-2xl text-gray-950 dark:text-white text-center", "Moderation" }
+            div { class: "flex justify-around",
+                tabs.iter().map(|(t, label)| {
+                    let class = match tab.get() == t {
+                        true => "border-b-2 border-indigo-500",
+                        false => "opacity-60",
+                    };
+                    rsx! {
+                        button { key: "{label}", class: "{class}", onclick: move |_| tab.set(*t), "{label}" }
+                    }
+                })
+            }
+            content
+        }
+    })
+}
+
+fn Loading(cx: Scope) -> Element {
+    cx.render(rsx! {
+        div { class: "grid place-content-center", Icon { icon: &Icons::CircleNotch, spin: true } }
+    })
+}
+
+fn ReportQueueList(cx: Scope) -> Element {
+    let set_toast = use_set(cx, TOAST);
+    let posts_state = use_atom_state(cx, POSTS);
+    let sc = cx.sc();
+    let refresh = use_state(cx, || 0);
+    let version = *refresh.get();
+    let future = use_future(cx, &version, |_| {
+        to_owned![set_toast];
+        async move {
+            match api_result(report_queue(sc).await) {
+                Ok(reports) => reports,
+                Err(err) => {
+                    set_toast(Some(err));
+                    vec![]
+                }
+            }
+        }
+    });
+    let Some(reports) = future.value() else { return cx.render(rsx! { Loading {} }) };
+    let on_resolve = move |target: ReportTarget, resolution: ReportResolution| {
+        to_owned![set_toast, posts_state, refresh];
+        let sc = cx.sc();
+        cx.spawn(async move {
+            match api_result(resolve_reports(sc, target, resolution).await) {
+                Ok(_) => {
+                    if let (ReportTarget::Post(post_id), ReportResolution::Action) =
+                        (target, resolution)
+                    {
+                        posts_state.with_mut(|posts| posts.retain(|p| p.id != post_id));
+                    }
+                    refresh.modify(|v| v + 1);
+                }
+                Err(err) => set_toast(Some(err)),
+            }
+        })
+    };
+    if reports.is_empty() {
+        return cx.render(rsx! { p { class: "text-center opacity-60", "Nothing to review" } });
+    }
+    cx.render(rsx! {
+        div { class: "flex flex-col gap-4",
+            reports.iter().map(|report| {
+                let target = report.target();
+                rsx! {
+                    div { key: "{report.target_kind}-{report.target_id}", class: "flex flex-col gap-2 p-4 rounded-md dark:bg-gray-800 bg-gray-100",
+                        div { class: "text-sm opacity-60", "{report.target_kind} by {report.account_name} - {report.report_count} reports" }
+                        div { "{report.body}" }
+                        div { class: "text-sm opacity-60", "{report.reasons}" }
+                        div { class: "flex gap-4 justify-end",
+                            button { onclick: move |_| on_resolve(target, ReportResolution::Dismiss), "Dismiss" }
+                            button { class: "text-red-500", onclick: move |_| on_resolve(target, ReportResolution::Action), "Delete" }
+                        }
+                    }
+                }
+            })
+        }
+    })
+}
+
+fn AdminPostList(cx: Scope) -> Element {
+    let set_toast = use_set(cx, TOAST);
+    let posts_state = use_atom_state(cx, POSTS);
+    let sc = cx.sc();
+    let refresh = use_state(cx, || 0);
+    let version = *refresh.get();
+    let future = use_future(cx, &version, |_| {
+        to_owned![set_toast];
+        async move {
+            match api_result(admin_posts(sc).await) {
+                Ok(posts) => posts,
+                Err(err) => {
+                    set_toast(Some(err));
+                    vec![]
+                }
+            }
+        }
+    });
+    let Some(posts) = future.value() else { return cx.render(rsx! { Loading {} }) };
+    let on_delete = move |post_id: i64| {
+        to_owned![set_toast, posts_state, refresh];
+        let sc = cx.sc();
+        cx.spawn(async move {
+            match api_result(admin_delete_post(sc, post_id).await) {
+                Ok(_) => {
+                    posts_state.with_mut(|posts| posts.retain(|p| p.id != post_id));
+                    refresh.modify(|v| v + 1);
+                }
+                Err(err) => set_toast(Some(err)),
+            }
+        })
+    };
+    cx.render(rsx! {
+        div { class: "flex flex-col gap-4",
+            posts.iter().map(|post| {
+                let post_id = post.id;
+                rsx! {
+                    div { key: "{post.id}", class: "flex flex-col gap-2 p-4 rounded-md dark:bg-gray-800 bg-gray-100",
+                        div { class: "text-sm opacity-60", "{post.account_name} - {post.created_at}" }
+                        div { "{post.body}" }
+                        button { class: "self-end text-red-500", onclick: move |_| on_delete(post_id), "Delete" }
+                    }
+                }
+            })
+        }
+    })
+}
+
+fn AdminCommentList(cx: Scope) -> Element {
+    let set_toast = use_set(cx, TOAST);
+    let sc = cx.sc();
+    let refresh = use_state(cx, || 0);
+    let version = *refresh.get();
+    let future = use_future(cx, &version, |_| {
+        to_owned![set_toast];
+        async move {
+            match api_result(admin_comments(sc).await) {
+                Ok(comments) => comments,
+                Err(err) => {
+                    set_toast(Some(err));
+                    vec![]
+                }
+            }
+        }
+    });
+    let Some(comments) = future.value() else { return cx.render(rsx! { Loading {} }) };
+    let on_delete = move |comment_id: i64| {
+        to_owned![set_toast, refresh];
+        let sc = cx.sc();
+        cx.spawn(async move {
+            match api_result(admin_delete_comment(sc, comment_id).await) {
+                Ok(_) => refresh.modify(|v| v + 1),
+                Err(err) => set_toast(Some(err)),
+            }
+        })
+    };
+    cx.render(rsx! {
+        div { class: "flex flex-col gap-4",
+            comments.iter().map(|comment| {
+                let comment_id = comment.id;
+                rsx! {
+                    div { key: "{comment.id}", class: "flex flex-col gap-2 p-4 rounded-md dark:bg-gray-800 bg-gray-100",
+                        div { class: "text-sm opacity-60", "{comment.account_name} on post {comment.post_id}" }
+                        div { "{comment.body}" }
+                        button { class: "self-end text-red-500", onclick: move |_| on_delete(comment_id), "Delete" }
+                    }
+                }
+            })
+        }
+    })
+}
+
+fn AdminAccountList(cx: Scope) -> Element {
+    let set_toast = use_set(cx, TOAST);
+    let sc = cx.sc();
+    let refresh = use_state(cx, || 0);
+    let version = *refresh.get();
+    let future = use_future(cx, &version, |_| {
+        to_owned![set_toast];
+        async move {
+            match api_result(admin_accounts(sc).await) {
+                Ok(accounts) => accounts,
+                Err(err) => {
+                    set_toast(Some(err));
+                    vec![]
+                }
+            }
+        }
+    });
+    let Some(accounts) = future.value() else { return cx.render(rsx! { Loading {} }) };
+    let on_suspend = move |account_id: i64, suspend: bool| {
+        to_owned![set_toast, refresh];
+        let sc = cx.sc();
+        cx.spawn(async move {
+            match api_result(suspend_account(sc, account_id, suspend).await) {
+                Ok(_) => refresh.modify(|v| v + 1),
+                Err(err) => set_toast(Some(err)),
+            }
+        })
+    };
+    cx.render(rsx! {
+        div { class: "flex flex-col gap-4",
+            accounts.iter().map(|account| {
+                let account_id = account.id;
+                let suspended = account.suspended_at.is_some();
+                let label = match suspended {
+                    true => "Unsuspend",
+                    false => "Suspend",
+                };
+                rsx! {
+                    div { key: "{account.id}", class: "flex justify-between items-center p-4 rounded-md dark:bg-gray-800 bg-gray-100",
+                        div { class: "flex flex-col",
+                            div { "{account.name}" }
+                            if account.is_admin {
+                                rsx! { div { class: "text-sm opacity-60", "admin" } }
+                            } else if suspended {
+                                rsx! { div { class: "text-sm text-red-500", "suspended" } }
+                            }
+                        }
+                        if !account.is_admin {
+                            rsx! {
+                                button { class: "text-red-500", onclick: move |_| on_suspend(account_id, !suspended), "{label}" }
+                            }
+                        }
+                    }
+                }
+            })
+        }
+    })
+}
+
+fn ModerationLogList(cx: Scope) -> Element {
+    let set_toast = use_set(cx, TOAST);
+    let sc = cx.sc();
+    let future = use_future(cx, (), |_| {
+        to_owned![set_toast];
+        async move {
+            match api_result(moderation_log(sc).await) {
+                Ok(actions) => actions,
+                Err(err) => {
+                    set_toast(Some(err));
+                    vec![]
+                }
+            }
+        }
+    });
+    let Some(actions) = future.value() else { return cx.render(rsx! { Loading {} }) };
+    cx.render(rsx! {
+        div { class: "flex flex-col gap-4",
+            actions.iter().map(|action| {
+                let moderator = action.moderator_name.clone().unwrap_or_else(|| models::DELETED_ACCOUNT_NAME.to_string());
+                rsx! {
+                    div { key: "{action.id}", class: "flex flex-col gap-1 p-4 rounded-md dark:bg-gray-800 bg-gray-100",
+                        div { class: "text-sm opacity-60", "{moderator} - {action.created_at}" }
+                        div { "{action.action} {action.target_kind} {action.target_id}" }
+                        div { class: "truncate", "{action.summary}" }
+                    }
+                }
+            })
+        }
+    })
+}
+
 fn ShowAccount(cx: Scope) -> Element {
-    let account = use_app_state(cx, ACCOUNT);
     let account_state = use_atom_state(cx, ACCOUNT);
+    let login_code_state = use_atom_state(cx, LOGIN_CODE);
+    let relations_state = use_atom_state(cx, RELATIONS);
     let view_state = use_atom_state(cx, VIEW);
     let posts_state = use_atom_state(cx, POSTS);
-    let login_code = match account {
-        Some(a) => a.login_code.to_string(),
-        None => String::default(),
-    };
+    let set_toast = use_set(cx, TOAST);
     let on_logout = move |_| {
         let sc = cx.sc();
         cx.spawn({
-            to_owned![account_state, view_state, posts_state];
+            to_owned![
+                account_state,
+                login_code_state,
+                relations_state,
+                view_state,
+                posts_state
+            ];
             async move {
-                if let Ok(_) = logout(sc).await {
+                if let Ok(_) = api_result(logout(sc).await) {
                     account_state.set(None);
+                    login_code_state.set(None);
+                    relations_state.set(None);
                     posts_state.with_mut(|posts| {
                         for post in posts {
                             post.liked_by_current_account = None;
@@ -1650,29 +8793,213 @@ fn ShowAccount(cx: Scope) -> Element {
             login_code_class.set("blur-sm");
         }
     };
-    let on_delete_account = move |_| {
+    let on_rotate_login_code = move |_| {
         let sc = cx.sc();
         cx.spawn({
-            to_owned![account_state, view_state];
+            to_owned![login_code_state, set_toast];
             async move {
-                if let Ok(_) = delete_account(sc).await {
-                    account_state.set(None);
-                    view_state.set(View::Posts);
+                match api_result(rotate_login_code(sc).await) {
+                    Ok(login_code) => login_code_state.set(Some(login_code)),
+                    Err(err) => set_toast(Some(err)),
                 }
             }
         })
     };
-    cx.render(rsx! {
-        div { class: "max-w-md mx-auto flex flex-col gap-4 pt-16 px-4 md:px-0 min-h-screen",
-            h1 { class: "text-2xl text-gray-950 dark:text-white text-center", "Account" }
+    let display_name_input = use_state(cx, String::new);
+    let name_input = use_state(cx, String::new);
+    let on_set_display_name = move |_| {
+        let sc = cx.sc();
+        cx.spawn({
+            to_owned![display_name_input, account_state, posts_state, set_toast];
+            async move {
+                match api_result(set_display_name(sc, display_name_input.get().clone()).await) {
+                    Ok(account) => {
+                        posts_state.with_mut(|posts| {
+                            for post in posts.iter_mut().filter(|p| p.account_id == account.id) {
+                                post.account_display_name = account.display_name.clone();
+                            }
+                        });
+                        account_state.set(Some(account));
+                    }
+                    Err(err) => set_toast(Some(err)),
+                }
+            }
+        })
+    };
+    let on_change_name = move |_| {
+        let sc = cx.sc();
+        cx.spawn({
+            to_owned![name_input, account_state, posts_state, set_toast];
+            async move {
+                match api_result(change_name(sc, name_input.get().clone()).await) {
+                    Ok(account) => {
+                        posts_state.with_mut(|posts| {
+                            for post in posts.iter_mut().filter(|p| p.account_id == account.id) {
+                                post.account_name = account.name.clone();
+                            }
+                        });
+                        account_state.set(Some(account));
+                    }
+                    Err(err) => set_toast(Some(err)),
+                }
+            }
+        })
+    };
+    let (handle, label) = match account_state.get() {
+        Some(account) => (account.name.clone(), account.label().to_string()),
+        None => (String::new(), String::new()),
+    };
+    let account_id = account_state
+        .get()
+        .as_ref()
+        .map(|a| a.id)
+        .unwrap_or_default();
+    let profile_fields = use_state(cx, ProfileFields::default);
+    use_future(cx, &account_id, |account_id| {
+        let sc = cx.sc();
+        to_owned![profile_fields];
+        async move {
+            if let Ok(fields) = api_result(account_profile_fields(sc, account_id).await) {
+                profile_fields.set(fields);
+            }
+        }
+    });
+    let on_set_profile_fields = move |_| {
+        let sc = cx.sc();
+        cx.spawn({
+            to_owned![profile_fields, set_toast];
+            async move {
+                match api_result(set_profile_fields(sc, profile_fields.get().clone()).await) {
+                    Ok(fields) => profile_fields.set(fields),
+                    Err(err) => set_toast(Some(err)),
+                }
+            }
+        })
+    };
+    let ProfileFields {
+        bio,
+        website,
+        pronouns,
+    } = profile_fields.get().clone();
+    let bio = bio.unwrap_or_default();
+    let website = website.unwrap_or_default();
+    let pronouns = pronouns.unwrap_or_default();
+    let redirect_days = HANDLE_REDIRECT_SECS / (24 * 60 * 60);
+    let cooldown_days = NAME_CHANGE_COOLDOWN_SECS / (24 * 60 * 60);
+    let confirm_delete = use_state(cx, || false);
+    let on_delete_account = move |mode: DeletionMode| {
+        let sc = cx.sc();
+        let account_id = account_state
+            .get()
+            .as_ref()
+            .map(|a| a.id)
+            .unwrap_or_default();
+        cx.spawn({
+            to_owned![
+                account_state,
+                login_code_state,
+                relations_state,
+                view_state,
+                posts_state,
+                set_toast
+            ];
+            async move {
+                match api_result(delete_account(sc, mode).await) {
+                    Ok(_) => {
+                        account_state.set(None);
+                        login_code_state.set(None);
+                        relations_state.set(None);
+                        posts_state.with_mut(|posts| posts.retain(|p| p.account_id != account_id));
+                        view_state.set(View::Posts);
+                    }
+                    Err(err) => set_toast(Some(err)),
+                }
+            }
+        })
+    };
+    let delete_account_section = match confirm_delete.get() {
+        true => rsx! {
+            div { class: "p-4 rounded-md dark:bg-gray-800 dark:text-white bg-gray-100 text-gray-950 flex flex-col gap-4",
+                p { "Your account and everything you posted is hidden right away and deleted for good after a grace period. Log back in with your login code before then to keep it." }
+                button { class: "text-red-500 text-left", onclick: move |_| on_delete_account(DeletionMode::Remove), "Delete my account and everything I posted" }
+                button { class: "text-red-500 text-left", onclick: move |_| on_delete_account(DeletionMode::Anonymise), "Delete my account, keep my posts as [deleted]" }
+                button { class: "text-left", onclick: move |_| confirm_delete.set(false), "Cancel" }
+            }
+        },
+        false => rsx! {
+            a { class: "cursor-pointer", onclick: move |_| confirm_delete.set(true), "Delete your account" }
+        },
+    };
+    let login_code = match login_code_state.get() {
+        Some(login_code) => rsx! {
             div { class: "p-4 rounded-md dark:bg-gray-800 dark:text-white bg-gray-100 text-gray-950",
                 p { "This is your login code. This is the only way back into your account." }
-                p { "Keep this code a secret, it's your password!" }
+                p { "Keep this code a secret, it's your password! It won't be shown again." }
                 p { class: "{login_code_class} cursor-pointer", onclick: toggle_login_code, "{login_code}" }
             }
+        },
+        None => rsx! {
+            div { class: "p-4 rounded-md dark:bg-gray-800 dark:text-white bg-gray-100 text-gray-950 flex flex-col gap-4",
+                p { "Your login code is only shown once. Lost it? Get a new one, the old one stops working." }
+                Button { onclick: on_rotate_login_code, "New login code" }
+            }
+        },
+    };
+    cx.render(rsx! {
+        div { class: "max-w-md mx-auto flex flex-col gap-4 pt-16 px-4 md:px-0 min-h-screen",
+            h1 { class: "text-2xl text-gray-950 dark:text-white text-center", "Account" }
+            login_code
+            div { class: "p-4 rounded-md dark:bg-gray-800 dark:text-white bg-gray-100 text-gray-950 flex flex-col gap-4",
+                p { "{label} (@{handle})" }
+                TextInput {
+                    name: "display_name",
+                    placeholder: "Display name",
+                    oninput: move |e: FormEvent| display_name_input.set(e.value.clone())
+                }
+                Button { onclick: on_set_display_name, "Save display name" }
+                TextInput {
+                    name: "handle",
+                    placeholder: "New handle",
+                    oninput: move |e: FormEvent| name_input.set(e.value.clone())
+                }
+                p { class: "text-sm opacity-60",
+                    "Your handle can change once every {cooldown_days} days. Links to the old one keep working for {redirect_days} days."
+                }
+                Button { onclick: on_change_name, "Change handle" }
+            }
+            div { class: "p-4 rounded-md dark:bg-gray-800 dark:text-white bg-gray-100 text-gray-950 flex flex-col gap-4",
+                p { "Shown on your profile, leave any of them blank." }
+                TextArea {
+                    name: "bio",
+                    placeholder: "Bio",
+                    value: bio.as_str(),
+                    rules: validation::BIO,
+                    oninput: move |e: FormEvent| profile_fields.with_mut(|f| f.bio = Some(e.value.clone()))
+                }
+                TextInput {
+                    name: "website",
+                    placeholder: "https://your.website",
+                    value: website.as_str(),
+                    oninput: move |e: FormEvent| profile_fields.with_mut(|f| f.website = Some(e.value.clone()))
+                }
+                TextInput {
+                    name: "pronouns",
+                    placeholder: "Pronouns",
+                    value: pronouns.as_str(),
+                    oninput: move |e: FormEvent| profile_fields.with_mut(|f| f.pronouns = Some(e.value.clone()))
+                }
+                Button { onclick: on_set_profile_fields, "Save profile" }
+            }
+            RelationsLoader {}
+            RelationsList {}
+            button { class: "underline text-left", onclick: move |_| view_state.set(View::Saved), "Saved posts" }
+            div { class: "p-4 rounded-md dark:bg-gray-800 dark:text-white bg-gray-100 text-gray-950 flex flex-col gap-4",
+                p { "Everything you've posted, commented and liked, as a json file." }
+                a { class: "underline", href: "/account/export", download: "", "Download your data" }
+            }
             div { class: "flex flex-col gap-16",
                 Button { onclick: on_logout, "Logout" }
-                a { class: "cursor-pointer", onclick: on_delete_account, "Delete your account" }
+                delete_account_section
             }
         }
     })
@@ -1707,6 +9034,11 @@ struct InputProps<'a> {
     placeholder: Option<&'a str>,
     #[props(optional)]
     kind: Option<&'a str>,
+    #[props(optional)]
+    value: Option<&'a str>,
+    /// Shows a character counter under a `TextArea`.
+    #[props(optional)]
+    rules: Option<validation::BodyRules>,
     name: &'a str,
 }
 
@@ -1716,6 +9048,8 @@ fn Input<'a>(cx: Scope<'a, InputProps<'a>>) -> Element {
         oninput,
         placeholder,
         name,
+        value,
+        ..
     } = cx.props;
     let kind = match kind {
         Some(k) => k,
@@ -1726,6 +9060,7 @@ fn Input<'a>(cx: Scope<'a, InputProps<'a>>) -> Element {
             r#type: "{kind}",
             name: "{name}",
             oninput: move |e| fwd_handler(oninput, e),
+            value: value.unwrap_or_default(),
             placeholder: placeholder.unwrap_or_default(),
             class: "p-3 rounded-md bg-white outline-none border border-gray-300 dark:border-gray-600 dark:bg-gray-700 dark:text-white text-gray-950"
         }
@@ -1737,6 +9072,7 @@ fn TextInput<'a>(cx: Scope<'a, InputProps<'a>>) -> Element {
         oninput,
         placeholder,
         name,
+        value,
         ..
     } = cx.props;
     cx.render(rsx! {
@@ -1744,6 +9080,7 @@ fn TextInput<'a>(cx: Scope<'a, InputProps<'a>>) -> Element {
             kind: "text",
             oninput: move |e| fwd_handler(oninput, e),
             name: "{name}",
+            value: value.unwrap_or_default(),
             placeholder: placeholder.unwrap_or_default()
         }
     })
@@ -1754,15 +9091,41 @@ fn TextArea<'a>(cx: Scope<'a, InputProps<'a>>) -> Element {
         oninput,
         placeholder,
         name,
+        value,
+        rules,
         ..
     } = cx.props;
+    // uncontrolled text areas keep their own copy for the counter
+    let typed = use_state(cx, String::new);
+    let text = value.unwrap_or(typed.get().as_str());
+    let counter = rules.map(|rules| {
+        let check = validation::validate_body(text, rules);
+        let color = match check.chars > rules.max_chars || check.lines > rules.max_lines {
+            true => "text-red-500",
+            false => "opacity-60",
+        };
+        let lines = match check.lines > rules.max_lines {
+            true => format!("{} lines, ", check.lines),
+            false => String::new(),
+        };
+        rsx! {
+            div { class: "text-sm text-right {color}", "{lines}{check.chars}/{rules.max_chars}" }
+        }
+    });
     cx.render(rsx! {
-        textarea {
-            rows: 2,
-            class: "p-3 rounded-md bg-white outline-none border border-gray-300 dark:border-gray-600 dark:bg-gray-700 dark:text-white text-gray-950",
-            oninput: move |e| fwd_handler(oninput, e),
-            name: "{name}",
-            placeholder: placeholder.unwrap_or_default()
+        div { class: "flex flex-col gap-1",
+            textarea {
+                rows: 2,
+                class: "p-3 rounded-md bg-white outline-none border border-gray-300 dark:border-gray-600 dark:bg-gray-700 dark:text-white text-gray-950",
+                oninput: move |e: FormEvent| {
+                    typed.set(e.value.clone());
+                    fwd_handler(oninput, e)
+                },
+                name: "{name}",
+                value: value.unwrap_or_default(),
+                placeholder: placeholder.unwrap_or_default()
+            }
+            counter
         }
     })
 }
@@ -1891,6 +9254,12 @@ enum Icons {
     PersonCircle,
     XCircle,
     CircleNotch,
+    Shield,
+    Flag,
+    Repeat,
+    Quotes,
+    Bookmark,
+    BookmarkFill,
 }
 
 #[inline_props]
@@ -1949,6 +9318,36 @@ fn Icon<'a>(cx: Scope, icon: &'a Icons, size: Option<usize>, spin: Option<bool>)
                     <path d="M128 24A104 104 0 1 0 232 128 104.11 104.11 0 0 0 128 24ZM74.08 197.5a64 64 0 0 1 107.84 0 87.83 87.83 0 0 1-107.84 0ZM96 120a32 32 0 1 1 32 32A32 32 0 0 1 96 120Zm97.76 66.41a79.66 79.66 0 0 0-36.06-28.75 48 48 0 1 0-59.4 0 79.66 79.66 0 0 0-36.06 28.75 88 88 0 1 1 131.52 0Z"></path></svg>"#
                 }
             },
+            Icons::Shield => rsx! {
+                span {
+                    dangerous_inner_html: r#"<svg xmlns="http://www.w3.org/2000/svg" width={width} height="{height}" fill="currentColor" viewBox="0 0 256 256"><path d="M208 40H48a16 16 0 0 0-16 16v56c0 52.72 25.52 84.67 46.93 102.19 23.06 18.86 46 25.26 47 25.53a8 8 0 0 0 4.2 0c1-.27 23.91-6.67 47-25.53C198.48 196.67 224 164.72 224 112V56a16 16 0 0 0-16-16Zm0 72c0 37.07-13.66 67.16-40.6 89.42a129.3 129.3 0 0 1-39.4 22.2 128.25 128.25 0 0 1-38.92-21.81C61.82 179.51 48 149.3 48 112V56h160Z"></path></svg>"#
+                }
+            },
+            Icons::Flag => rsx! {
+                span {
+                    dangerous_inner_html: r#"<svg xmlns="http://www.w3.org/2000/svg" width={width} height="{height}" fill="currentColor" viewBox="0 0 256 256"><path d="M34.76 42A8 8 0 0 0 32 48v168a8 8 0 0 0 16 0v-44.28c26.79-21.16 49.87-9.75 76.45 3.41 16.4 8.11 34.06 16.85 53 16.85 13.93 0 28.54-4.75 43.82-18a8 8 0 0 0 2.76-6V48a8 8 0 0 0-13.27-6c-28 24.23-51.72 12.49-79.21-1.12C103.07 26.76 70.78 10.79 34.76 42ZM208 164.25c-26.79 21.16-49.87 9.74-76.45-3.41-25-12.35-52.81-26.13-83.55-8.4V51.79c26.79-21.16 49.87-9.75 76.45 3.4 25 12.35 52.82 26.13 83.55 8.4Z"></path></svg>"#
+                }
+            },
+            Icons::Bookmark => rsx! {
+                span {
+                    dangerous_inner_html: r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" fill="currentColor" viewBox="0 0 256 256"><path d="M184 32H72a16 16 0 0 0-16 16v176a8 8 0 0 0 12.24 6.78L128 193.43l59.77 37.35A8 8 0 0 0 200 224V48a16 16 0 0 0-16-16Zm0 177.57-51.77-32.35a8 8 0 0 0-8.48 0L72 209.57V48h112Z"></path></svg>"#
+                }
+            },
+            Icons::BookmarkFill => rsx! {
+                span {
+                    dangerous_inner_html: r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" fill="currentColor" viewBox="0 0 256 256"><path d="M200 48v176a8 8 0 0 1-12.24 6.78L128 193.43l-59.77 37.35A8 8 0 0 1 56 224V48a16 16 0 0 1 16-16h112a16 16 0 0 1 16 16Z"></path></svg>"#
+                }
+            },
+            Icons::Repeat => rsx! {
+                span {
+                    dangerous_inner_html: r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" fill="currentColor" viewBox="0 0 256 256"><path d="M24 128a72.08 72.08 0 0 1 72-72h108.69l-10.35-10.34a8 8 0 0 1 11.32-11.32l24 24a8 8 0 0 1 0 11.32l-24 24a8 8 0 0 1-11.32-11.32L204.69 72H96a56.06 56.06 0 0 0-56 56 8 8 0 0 1-16 0Zm200-8a8 8 0 0 0-8 8 56.06 56.06 0 0 1-56 56H51.31l10.35-10.34a8 8 0 0 0-11.32-11.32l-24 24a8 8 0 0 0 0 11.32l24 24a8 8 0 0 0 11.32-11.32L51.31 200H160a72.08 72.08 0 0 0 72-72 8 8 0 0 0-8-8Z"></path></svg>"#
+                }
+            },
+            Icons::Quotes => rsx! {
+                span {
+                    dangerous_inner_html: r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" fill="currentColor" viewBox="0 0 256 256"><path d="M100 56H40a16 16 0 0 0-16 16v64a16 16 0 0 0 16 16h60v8a32 32 0 0 1-32 32 8 8 0 0 0 0 16 48.05 48.05 0 0 0 48-48V72a16 16 0 0 0-16-16Zm0 96H40V72h60Zm116-96h-60a16 16 0 0 0-16 16v64a16 16 0 0 0 16 16h60v8a32 32 0 0 1-32 32 8 8 0 0 0 0 16 48.05 48.05 0 0 0 48-48V72a16 16 0 0 0-16-16Zm0 96h-60V72h60Z"></path></svg>"#
+                }
+            },
             Icons::XCircle => rsx! {
                 span {
                     dangerous_inner_html: r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" fill="currentColor" viewBox="0 0 256 256"><path d="M165.66 101.66 139.31 128l26.35 26.34a8 8 0 0 1-11.32 11.32L128 139.31l-26.34 26.35a8 8 0 0 1-11.32-11.32L116.69 128 90.34 101.66a8 8 0 0 1 11.32-11.32L128 116.69l26.34-26.35a8 8 0 0 1 11.32 11.32ZM232 128A104 104 0 1 1 128 24 104.11 104.11 0 0 1 232 128Zm-16 0a88 88 0 1 0-88 88A88.1 88.1 0 0 0 216 128Z"></path></svg>"#